@@ -0,0 +1,287 @@
+//! Folding of headwords to ascii, as used for the `data-ascii` attribute of entries.
+//!
+//! Stress and syllable marks are dropped. Other characters are looked up in a table of
+//! replacements, then decomposed (compatibility decomposition, so that `ᵉ` becomes `e` and `ﬁ`
+//! becomes `fi`) with the combining marks dropped and the remaining characters folded in turn.
+//! What is still not ascii is handled as the `Unmapped` policy says.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+
+/// Replacements for the letters and marks of Webster and GCIDE that do not decompose to ascii,
+/// sorted by character.
+pub const DEFAULT_TABLE: &[(char, &str)] = &[
+    ('\u{00A3}', "L"),    // £
+    ('\u{00A7}', "S"),    // §
+    ('\u{00AB}', "\""),   // «
+    ('\u{00B0}', "deg"),  // °
+    ('\u{00B7}', "."),    // ·
+    ('\u{00BB}', "\""),   // »
+    ('\u{00C6}', "AE"),   // Æ
+    ('\u{00D0}', "D"),    // Ð
+    ('\u{00D7}', "x"),    // ×
+    ('\u{00D8}', "O"),    // Ø
+    ('\u{00DE}', "Th"),   // Þ
+    ('\u{00DF}', "ss"),   // ß
+    ('\u{00E6}', "ae"),   // æ
+    ('\u{00F0}', "d"),    // ð
+    ('\u{00F7}', "/"),    // ÷
+    ('\u{00F8}', "o"),    // ø
+    ('\u{00FE}', "th"),   // þ
+    ('\u{0110}', "D"),    // Đ
+    ('\u{0111}', "d"),    // đ
+    ('\u{0126}', "H"),    // Ħ
+    ('\u{0127}', "h"),    // ħ
+    ('\u{0131}', "i"),    // ı
+    ('\u{0141}', "L"),    // Ł
+    ('\u{0142}', "l"),    // ł
+    ('\u{014A}', "NG"),   // Ŋ
+    ('\u{014B}', "ng"),   // ŋ
+    ('\u{0152}', "OE"),   // Œ
+    ('\u{0153}', "oe"),   // œ
+    ('\u{0166}', "T"),    // Ŧ
+    ('\u{0167}', "t"),    // ŧ
+    ('\u{0186}', "O"),    // Ɔ
+    ('\u{018E}', "E"),    // Ǝ
+    ('\u{018F}', "E"),    // Ə
+    ('\u{0190}', "E"),    // Ɛ
+    ('\u{01A9}', "Sh"),   // Ʃ
+    ('\u{01B1}', "U"),    // Ʊ
+    ('\u{01B7}', "Z"),    // Ʒ
+    ('\u{01BF}', "w"),    // ƿ
+    ('\u{01DD}', "e"),    // ǝ
+    ('\u{01F7}', "W"),    // Ƿ
+    ('\u{021C}', "Y"),    // Ȝ
+    ('\u{021D}', "y"),    // ȝ
+    ('\u{0245}', "U"),    // Ʌ
+    ('\u{0251}', "a"),    // ɑ
+    ('\u{0254}', "o"),    // ɔ
+    ('\u{0259}', "e"),    // ə
+    ('\u{025B}', "e"),    // ɛ
+    ('\u{026A}', "i"),    // ɪ
+    ('\u{0283}', "sh"),   // ʃ
+    ('\u{028A}', "u"),    // ʊ
+    ('\u{028C}', "u"),    // ʌ
+    ('\u{0292}', "z"),    // ʒ
+    ('\u{02BC}', "'"),    // ʼ
+    ('\u{02D8}', ""),     // ˘
+    ('\u{0391}', "A"),    // Greek capitals
+    ('\u{0392}', "B"),
+    ('\u{0393}', "G"),
+    ('\u{0394}', "D"),
+    ('\u{0395}', "E"),
+    ('\u{0396}', "Z"),
+    ('\u{0397}', "E"),
+    ('\u{0398}', "Th"),
+    ('\u{0399}', "I"),
+    ('\u{039A}', "K"),
+    ('\u{039B}', "L"),
+    ('\u{039C}', "M"),
+    ('\u{039D}', "N"),
+    ('\u{039E}', "X"),
+    ('\u{039F}', "O"),
+    ('\u{03A0}', "P"),
+    ('\u{03A1}', "R"),
+    ('\u{03A3}', "S"),
+    ('\u{03A4}', "T"),
+    ('\u{03A5}', "Y"),
+    ('\u{03A6}', "Ph"),
+    ('\u{03A7}', "Ch"),
+    ('\u{03A8}', "Ps"),
+    ('\u{03A9}', "O"),
+    ('\u{03B1}', "a"),    // Greek small letters
+    ('\u{03B2}', "b"),
+    ('\u{03B3}', "g"),
+    ('\u{03B4}', "d"),
+    ('\u{03B5}', "e"),
+    ('\u{03B6}', "z"),
+    ('\u{03B7}', "e"),
+    ('\u{03B8}', "th"),
+    ('\u{03B9}', "i"),
+    ('\u{03BA}', "k"),
+    ('\u{03BB}', "l"),
+    ('\u{03BC}', "m"),
+    ('\u{03BD}', "n"),
+    ('\u{03BE}', "x"),
+    ('\u{03BF}', "o"),
+    ('\u{03C0}', "p"),
+    ('\u{03C1}', "r"),
+    ('\u{03C2}', "s"),
+    ('\u{03C3}', "s"),
+    ('\u{03C4}', "t"),
+    ('\u{03C5}', "y"),
+    ('\u{03C6}', "ph"),
+    ('\u{03C7}', "ch"),
+    ('\u{03C8}', "ps"),
+    ('\u{03C9}', "o"),
+    ('\u{1E9E}', "SS"),   // ẞ
+    ('\u{2010}', "-"),    // ‐
+    ('\u{2012}', "-"),    // ‒
+    ('\u{2013}', "-"),    // –
+    ('\u{2014}', "--"),   // —
+    ('\u{2015}', "--"),   // ―
+    ('\u{2016}', "||"),   // ‖
+    ('\u{2018}', "'"),    // ‘
+    ('\u{2019}', "'"),    // ’
+    ('\u{201A}', ","),    // ‚
+    ('\u{201C}', "\""),   // “
+    ('\u{201D}', "\""),   // ”
+    ('\u{201E}', "\""),   // „
+    ('\u{2032}', "'"),    // ′
+    ('\u{221A}', "sqrt"), // √
+    ('\u{2225}', "||"),   // ∥
+    ('\u{2248}', "~="),   // ≈
+    ('\u{266D}', "b"),    // ♭
+    ('\u{266F}', "#"),    // ♯
+    ('\u{2C6D}', "A"),    // Ɑ
+    ('\u{A74E}', "OO"),   // Ꝏ
+    ('\u{A74F}', "oo"),   // ꝏ
+    ('\u{A7AE}', "I"),    // Ɪ
+    ('\u{FFFD}', "?"),    // �
+];
+
+/// What to do with a character that cannot be folded to ascii.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unmapped {
+    /// Fail with a `FoldError`.
+    Fail,
+    /// Keep the character as it is.
+    Keep,
+    /// Put this character in its place.
+    Replace(char),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoldError {
+    pub character: char,
+    /// Byte offset of the character in the word given to `fold`.
+    pub offset: usize,
+}
+
+impl Display for FoldError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "no ascii folding for {:?} (U+{:04X}) at byte {}",
+               self.character, self.character as u32, self.offset)
+    }
+}
+
+impl Error for FoldError {}
+
+#[derive(Clone, Debug)]
+pub struct AsciiFolder {
+    /// Replacements taking precedence over `DEFAULT_TABLE`.
+    pub table: HashMap<char, String>,
+    /// Whether to use `DEFAULT_TABLE` for characters missing from `table`.
+    pub default_table: bool,
+    pub unmapped: Unmapped,
+}
+
+impl Default for AsciiFolder {
+    fn default() -> AsciiFolder {
+        AsciiFolder { table: HashMap::new(), default_table: true, unmapped: Unmapped::Fail }
+    }
+}
+
+impl AsciiFolder {
+    pub fn new(unmapped: Unmapped) -> AsciiFolder {
+        AsciiFolder { unmapped, ..AsciiFolder::default() }
+    }
+
+    fn lookup(&self, c: char) -> Option<&str> {
+        if let Some(replacement) = self.table.get(&c) {
+            return Some(replacement);
+        }
+        if !self.default_table {
+            return None;
+        }
+        DEFAULT_TABLE.binary_search_by_key(&c, |&(c, _)| c).ok().map(|i| DEFAULT_TABLE[i].1)
+    }
+
+    /// Push the folding of `c` to `out`, returning the first character that has none.
+    fn push_char(&self, out: &mut String, c: char) -> Result<(), char> {
+        if c.is_ascii() {
+            out.push(c);
+            return Ok(());
+        }
+        if let Some(replacement) = self.lookup(c) {
+            out.push_str(replacement);
+            return Ok(());
+        }
+        let mut decomposed = Vec::new();
+        decompose_compatible(c, |d| decomposed.push(d));
+        if decomposed == [c] {
+            return Err(c);
+        }
+        for d in decomposed {
+            if !is_combining_mark(d) {
+                self.push_char(out, d)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fold(&self, word: &str) -> Result<String, FoldError> {
+        let mut out = String::with_capacity(word.len());
+        for (offset, c) in word.char_indices() {
+            if c == '´' || c == '•' {
+                continue;
+            }
+            let len = out.len();
+            if let Err(character) = self.push_char(&mut out, c) {
+                out.truncate(len);
+                match self.unmapped {
+                    Unmapped::Fail => return Err(FoldError { character, offset }),
+                    Unmapped::Keep => out.push(c),
+                    Unmapped::Replace(replacement) => out.push(replacement),
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AsciiFolder, FoldError, Unmapped, DEFAULT_TABLE};
+
+    #[test]
+    fn fold_test() {
+        assert!(DEFAULT_TABLE.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        // every cased letter has its other case, folded alike
+        let folder = AsciiFolder::default();
+        for &(c, replacement) in DEFAULT_TABLE {
+            let other: Vec<char> =
+                if c.is_lowercase() { c.to_uppercase().collect() } else { c.to_lowercase().collect() };
+            if other == [c] || other.iter().all(char::is_ascii) {
+                continue;
+            }
+            let other: String = other.into_iter().collect();
+            let folded = folder.fold(&other).unwrap_or_else(|err| panic!("{:?}: {}", c, err));
+            assert_eq!(folded.to_lowercase(), replacement.to_lowercase(), "{:?} and {:?}", c, other);
+        }
+
+        assert_eq!(folder.fold("Æ•gi´lops").unwrap(), "AEgilops");
+        assert_eq!(folder.fold("Stra•ße").unwrap(), "Strasse");
+        assert_eq!(folder.fold("\u{101}b\u{12B}dan").unwrap(), "abidan");
+        assert_eq!(folder.fold("\u{3BB}\u{3CC}\u{3B3}\u{3BF}\u{3C2}").unwrap(), "logos");
+        assert_eq!(folder.fold("\u{1E3}").unwrap(), "ae");
+        assert_eq!(folder.fold("Mc\u{1D49}").unwrap(), "Mce");
+        assert_eq!(folder.fold("Ça ira").unwrap(), "Ca ira");
+        assert_eq!(folder.fold("a\u{2603}b"), Err(FoldError { character: '\u{2603}', offset: 1 }));
+
+        // the characters g673-unescape emits
+        let gcide = "<>ÇüéâäàåçêëèïîìÄÉæÆôöòûùÿÖÜ£áíóúñÑ″\u{FFFD}′”§“ā‘♯♭īēĕĭŏ–—Œœōūǒǣ∥ŭă˘ȳþã÷≈°•√";
+        assert!(folder.fold(gcide).unwrap().is_ascii());
+        assert_eq!(folder.fold("ʒɔȝħĦŦǝꝏƏ").unwrap(), "zoyhHTeooE");
+        assert_eq!(folder.fold("ƿɛʃʊʌɑɪ").unwrap(), "weshuuai");
+
+        let mut custom = AsciiFolder::new(Unmapped::Replace('?'));
+        custom.table.insert('ß', "sz".to_string());
+        assert_eq!(custom.fold("Stra•ße \u{2603}").unwrap(), "Strasze ?");
+        custom.default_table = false;
+        assert_eq!(custom.fold("Æon").unwrap(), "?on");
+        assert_eq!(AsciiFolder::new(Unmapped::Keep).fold("a\u{2603}").unwrap(), "a\u{2603}");
+    }
+}
@@ -0,0 +1,307 @@
+//! Headword completion over a finite-state transducer, stored in a file that can be mapped into
+//! memory instead of being rebuilt or read on every start.
+//!
+//! Keys are the `data-ascii` words of entries in lowercase, each mapping to the tocids of the
+//! entries sharing it. The file is laid out as:
+//!
+//! - the magic bytes `NMSTRAC1`;
+//! - the number of 32-bit words in the tocid table, as a little-endian `u64`;
+//! - the tocid table: for each key, the number of its tocids followed by the tocids, as
+//!   little-endian `u32`s;
+//! - the FST, mapping each key to the index of its tocids in the table.
+
+use fst::map::Stream;
+use fst::{Automaton, IntoStreamer, Map, MapBuilder, Streamer};
+use memmap2::Mmap;
+use parser::{fold_to_ascii, TaggedEntry};
+use regex_automata::dense::{self, DenseDFA};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+const MAGIC: &[u8] = b"NMSTRAC1";
+const HEADER_LEN: usize = 16;
+
+/// The key `word` is stored and looked up under.
+pub fn normalize(word: &str) -> String {
+    fold_to_ascii(word).to_lowercase()
+}
+
+fn invalid_data<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Collects keys and their tocids, to be written out as a completion file.
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    keys: BTreeMap<String, BTreeSet<u32>>,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder::default()
+    }
+
+    pub fn insert(&mut self, word: &str, tocid: u32) {
+        self.keys.entry(normalize(word)).or_default().insert(tocid);
+    }
+
+    pub fn add(&mut self, entry: &TaggedEntry) {
+        self.insert(&entry.word, entry.tocid);
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut table = Vec::new();
+        let mut map = MapBuilder::memory();
+        for (key, tocids) in &self.keys {
+            map.insert(key, table.len() as u64).map_err(invalid_data)?;
+            table.push(tocids.len() as u32);
+            table.extend(tocids);
+        }
+        let map = map.into_inner().map_err(invalid_data)?;
+
+        out.write_all(MAGIC)?;
+        out.write_all(&(table.len() as u64).to_le_bytes())?;
+        for word in table {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        out.write_all(&map)?;
+        out.flush()
+    }
+
+    pub fn build(&self) -> Autocomplete<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes).unwrap();
+        Autocomplete::new(bytes).unwrap()
+    }
+}
+
+/// The bytes of `data` from `start` on.
+struct Tail<D> {
+    data: D,
+    start: usize,
+}
+
+impl<D: AsRef<[u8]>> AsRef<[u8]> for Tail<D> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data.as_ref()[self.start..]
+    }
+}
+
+/// A completion file, in memory or mapped from disk.
+pub struct Autocomplete<D> {
+    map: Map<Tail<D>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub key: String,
+    pub tocids: Vec<u32>,
+}
+
+/// Completions in key order.
+pub struct Completions<'a, A: Automaton> {
+    stream: Stream<'a, A>,
+    table: &'a [u8],
+}
+
+impl<'a, A: Automaton> Iterator for Completions<'a, A> {
+    type Item = Completion;
+
+    fn next(&mut self) -> Option<Completion> {
+        let table = self.table;
+        self.stream.next().map(|(key, index)| Completion {
+            key: String::from_utf8_lossy(key).into_owned(),
+            tocids: tocids(table, index),
+        })
+    }
+}
+
+/// The `i`th 32-bit word of the tocid table.
+fn word(table: &[u8], i: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&table[4 * i..4 * i + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+/// The tocids at `index` in the tocid table, which `Autocomplete::new` has checked is in bounds.
+fn tocids(table: &[u8], index: u64) -> Vec<u32> {
+    let index = index as usize;
+    (0..word(table, index) as usize).map(|i| word(table, index + 1 + i)).collect()
+}
+
+/// Whether the tocids at `index` lie within the tocid table.
+fn in_table(table: &[u8], index: u64) -> bool {
+    let len = (table.len() / 4) as u64;
+    index < len && index + 1 + u64::from(word(table, index as usize)) <= len
+}
+
+/// Matches keys starting with its bytes.
+struct Prefix(Vec<u8>);
+
+impl Automaton for Prefix {
+    /// How many bytes of the prefix have been matched, or `None` on a mismatch.
+    type State = Option<usize>;
+
+    fn start(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_match(&self, state: &Option<usize>) -> bool {
+        *state == Some(self.0.len())
+    }
+
+    fn can_match(&self, state: &Option<usize>) -> bool {
+        state.is_some()
+    }
+
+    fn will_always_match(&self, state: &Option<usize>) -> bool {
+        self.is_match(state)
+    }
+
+    fn accept(&self, state: &Option<usize>, byte: u8) -> Option<usize> {
+        match *state {
+            Some(matched) if matched == self.0.len() => Some(matched),
+            Some(matched) if self.0[matched] == byte => Some(matched + 1),
+            _ => None,
+        }
+    }
+}
+
+/// Translate a wildcard pattern, where `*` matches any text and `?` any character, to a regex.
+fn wildcard_regex(pattern: &str) -> String {
+    let mut regex = String::new();
+    for c in normalize(pattern).chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => {
+                if "\\.+*?()|[]{}^$#&-~".contains(c) {
+                    regex.push('\\');
+                }
+                regex.push(c);
+            }
+        }
+    }
+    regex
+}
+
+impl Autocomplete<Mmap> {
+    /// Map the completion file at `path` into memory.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Autocomplete<Mmap>> {
+        let file = File::open(path)?;
+        // the file must not be modified while mapped; completion files are only ever replaced
+        let mmap = unsafe { Mmap::map(&file)? };
+        Autocomplete::new(mmap)
+    }
+}
+
+impl<D: AsRef<[u8]>> Autocomplete<D> {
+    pub fn new(data: D) -> io::Result<Autocomplete<D>> {
+        let bytes = data.as_ref();
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("not a completion file"));
+        }
+        let mut len = [0; 8];
+        len.copy_from_slice(&bytes[MAGIC.len()..HEADER_LEN]);
+        let start = (u64::from_le_bytes(len) as usize).checked_mul(4)
+            .and_then(|table_len| table_len.checked_add(HEADER_LEN))
+            .filter(|&start| start <= bytes.len())
+            .ok_or_else(|| invalid_data("truncated completion file"))?;
+        let map = Map::new(Tail { data, start }).map_err(invalid_data)?;
+        let complete = Autocomplete { map };
+        let mut stream = complete.map.stream();
+        while let Some((_, index)) = stream.next() {
+            if !in_table(complete.table(), index) {
+                return Err(invalid_data("corrupt completion file"));
+            }
+        }
+        Ok(complete)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    fn table(&self) -> &[u8] {
+        let tail = self.map.as_fst().as_inner();
+        &tail.data.as_ref()[HEADER_LEN..tail.start]
+    }
+
+    fn completions<'a, A: Automaton>(&'a self, stream: Stream<'a, A>) -> Completions<'a, A> {
+        Completions { stream, table: self.table() }
+    }
+
+    /// The tocids of the entries of `word`.
+    pub fn get(&self, word: &str) -> Vec<u32> {
+        self.map.get(normalize(word)).map_or_else(Vec::new, |index| tocids(self.table(), index))
+    }
+
+    /// Keys starting with `prefix`.
+    pub fn prefix(&self, prefix: &str) -> Completions<'_, impl Automaton> {
+        self.completions(self.map.search(Prefix(normalize(prefix).into_bytes())).into_stream())
+    }
+
+    /// Keys from `start` up to but excluding `end`.
+    pub fn range(&self, start: &str, end: &str) -> Completions<'_, impl Automaton> {
+        self.completions(self.map.range().ge(normalize(start)).lt(normalize(end)).into_stream())
+    }
+
+    /// Keys matched as a whole by the regular expression `pattern`.
+    pub fn regex(&self, pattern: &str) -> Result<Completions<'_, impl Automaton>, regex_automata::Error> {
+        let dfa: DenseDFA<Vec<usize>, usize> = dense::Builder::new().anchored(true).build(pattern)?;
+        Ok(self.completions(self.map.search(dfa).into_stream()))
+    }
+
+    /// Keys matched by `pattern`, where `*` stands for any text and `?` for any character.
+    pub fn wildcard(&self, pattern: &str) -> Result<Completions<'_, impl Automaton>, regex_automata::Error> {
+        self.regex(&wildcard_regex(pattern))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Autocomplete, Builder};
+
+    #[test]
+    fn complete_test() {
+        let mut builder = Builder::new();
+        for &(word, tocid) in &[("Abbey", 0xA), ("Abide", 0xB), ("Abide", 0xC), ("Abode", 0xD),
+                                ("Zöll´ner’s Lines", 0xE), ("abider", 0xF)] {
+            builder.insert(word, tocid);
+        }
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        let complete = Autocomplete::new(&bytes[..]).unwrap();
+        assert_eq!(complete.len(), 5);
+
+        let keys = |completions: &mut dyn Iterator<Item = super::Completion>| {
+            completions.map(|completion| completion.key).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&mut complete.prefix("AB")), ["abbey", "abide", "abider", "abode"]);
+        assert_eq!(keys(&mut complete.prefix("zoll")), ["zollner's lines"]);
+        assert_eq!(keys(&mut complete.range("abc", "abo")), ["abide", "abider"]);
+        assert_eq!(keys(&mut complete.regex("ab[io]de").unwrap()), ["abide", "abode"]);
+        assert_eq!(keys(&mut complete.wildcard("a?b*").unwrap()), ["abbey"]);
+        assert_eq!(keys(&mut complete.wildcard("*'s *").unwrap()), ["zollner's lines"]);
+        assert!(complete.regex("(").is_err());
+        assert_eq!(complete.prefix("abid").next().unwrap().tocids, [0xB, 0xC]);
+        assert_eq!(complete.get("A•bide´"), [0xB, 0xC]);
+        assert!(complete.get("Abid").is_empty());
+        assert_eq!(builder.build().get("abider"), [0xF]);
+
+        let path = ::std::env::temp_dir().join(format!("nomster-complete-{}", ::std::process::id()));
+        ::write_file(&path, &bytes, ::Backup::None).unwrap();
+        assert_eq!(Autocomplete::open(&path).unwrap().get("abode"), [0xD]);
+        ::std::fs::remove_file(&path).unwrap();
+        assert!(Autocomplete::new(&b"NMSTRAC1\xff\0\0\0\0\0\0\0"[..]).is_err());
+        let mut corrupt = bytes.clone();
+        corrupt[16..20].copy_from_slice(&100u32.to_le_bytes());
+        assert!(Autocomplete::new(&corrupt[..]).is_err());
+    }
+}
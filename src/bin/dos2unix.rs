@@ -1,9 +1,10 @@
 extern crate encoding;
-#[macro_use]
+extern crate nomster;
 extern crate structopt;
 
 use std::fs::File;
-use std::io::{Error, ErrorKind, Read, Write};
+use nomster::Backup;
+use std::io::{Error, ErrorKind, Read};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
@@ -14,6 +15,9 @@ struct Opt {
     #[structopt(name = "OUTFILE", help = "output path (overwrites INFILE if not given)",
                 parse(from_os_str))]
     output: Option<PathBuf>,
+    #[structopt(long = "backup", default_value = "none",
+                help = "keep a copy of the replaced file: none, bak or timestamp")]
+    backup: Backup,
 }
 
 pub fn read_dos<P: AsRef<Path>>(path: P) -> Result<String, Error> {
@@ -28,15 +32,14 @@ pub fn read_dos<P: AsRef<Path>>(path: P) -> Result<String, Error> {
     })
 }
 
-fn write_unix(contents: &str, output: &Path) -> Result<(), Error> {
+fn write_unix(contents: &str, output: &Path, backup: Backup) -> Result<(), Error> {
     let contents = contents.replace("\r\n", "\n");
-    File::create(output)?.write_all(contents.as_bytes())?;
-    Ok(())
+    nomster::write_file(output, contents.as_bytes(), backup)
 }
 
 fn main() {
     let opt = Opt::from_args();
     let output = opt.output.as_ref().unwrap_or(&opt.input);
     let contents = read_dos(&opt.input).unwrap();
-    write_unix(&contents, output).unwrap();
+    write_unix(&contents, output, opt.backup).unwrap();
 }
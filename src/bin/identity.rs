@@ -1,9 +1,8 @@
 extern crate nomster;
 
-#[macro_use]
 extern crate structopt;
 
-use nomster::Parser;
+use nomster::{Backup, Parser};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
@@ -13,26 +12,32 @@ struct Opt {
     input: PathBuf,
     #[structopt(name = "OUTFILE", help = "output file (default: overwrite)", parse(from_os_str))]
     output: Option<PathBuf>,
+    #[structopt(long = "backup", default_value = "none",
+                help = "keep a copy of the replaced file: none, bak or timestamp")]
+    backup: Backup,
 }
 
-fn patch(contents: &str, output: &Path) -> Result<(), std::io::Error> {
-    use std::io::Write;
+fn patch(contents: &str, output: &Path, backup: Backup) -> Result<(), std::io::Error> {
     let mut patched = String::with_capacity(contents.len());
     let mut entry_iter = Parser::new(contents);
-    while let Some((skipped, entry)) = entry_iter.next() {
+    for (skipped, entry) in entry_iter.by_ref() {
         use std::fmt::Write;
         patched.push_str(skipped);
-        let entry = entry.unwrap();
-        write!(patched, "{}", entry).unwrap();
+        match entry {
+            Ok(entry) => write!(patched, "{}", entry).unwrap(),
+            Err(err) => {
+                eprintln!("{}", err);
+                patched.push_str(err.entry);
+            }
+        }
     }
     patched.push_str(entry_iter.remaining());
-    std::fs::File::create(output)?.write_all(patched.as_bytes())?;
-    Ok(())
+    nomster::write_file(output, patched.as_bytes(), backup)
 }
 
 fn main() {
     let opt = Opt::from_args();
     let output = opt.output.as_ref().unwrap_or(&opt.input);
     let contents = nomster::read_file(&opt.input).unwrap();
-    patch(&contents, &output).unwrap();
+    patch(&contents, output, opt.backup).unwrap();
 }
@@ -0,0 +1,61 @@
+extern crate nomster;
+extern crate serde_json;
+extern crate structopt;
+
+use nomster::parser::TaggedEntry;
+use nomster::{AtomicFile, Backup, Collator, EntryReader, ParseOptions};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+struct Opt {
+    #[structopt(name = "INFILE", help = "webster html file", parse(from_os_str))]
+    input: PathBuf,
+    #[structopt(name = "OUTFILE", help = "json lines output (default: stdout)", parse(from_os_str))]
+    output: Option<PathBuf>,
+    #[structopt(long = "lenient", help = "keep unknown markup instead of skipping the entry")]
+    lenient: bool,
+    #[structopt(long = "collate", help = "write entries in dictionary order instead of source order")]
+    collate: bool,
+}
+
+fn write_entry<W: Write>(out: &mut W, entry: &TaggedEntry) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, entry)?;
+    writeln!(out)
+}
+
+fn export<W: Write>(entries: EntryReader<BufReader<File>>, collate: bool, mut out: W) -> io::Result<()> {
+    let mut collated = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        match entry.parse() {
+            Ok(entry) if collate => collated.push(entry.into_owned()),
+            Ok(entry) => write_entry(&mut out, &entry)?,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    let collator = Collator::new();
+    collated.sort_by_cached_key(|entry| collator.key(&entry.word));
+    for entry in &collated {
+        write_entry(&mut out, entry)?;
+    }
+    out.flush()
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    let input = BufReader::new(File::open(&opt.input).unwrap());
+    let entries = EntryReader::with_options(input, ParseOptions { lenient: opt.lenient }).max_skipped(4096);
+    let result = match opt.output {
+        Some(ref output) => {
+            let mut out = BufWriter::new(AtomicFile::create(output).unwrap());
+            export(entries, opt.collate, &mut out)
+                .and_then(|()| out.into_inner().map_err(|err| err.into_error()))
+                .and_then(|file| file.commit(Backup::None))
+        }
+        None => export(entries, opt.collate, io::stdout().lock()),
+    };
+    result.unwrap();
+}
@@ -0,0 +1,70 @@
+extern crate nomster;
+extern crate structopt;
+
+use nomster::refs::RefGraph;
+use nomster::{AtomicFile, Backup};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+struct Opt {
+    #[structopt(name = "FILE", help = "webster html files", parse(from_os_str), required = true)]
+    inputs: Vec<PathBuf>,
+    #[structopt(short = "o", long = "output", help = "output file (default: stdout)", parse(from_os_str))]
+    output: Option<PathBuf>,
+    #[structopt(long = "format", default_value = "dot", help = "output format: dot or graphml")]
+    format: String,
+    #[structopt(long = "around", help = "only output the entries near this headword")]
+    around: Option<String>,
+    #[structopt(long = "hops", default_value = "1", help = "number of links to follow from --around")]
+    hops: usize,
+}
+
+fn export<W: Write>(graph: &RefGraph, mut out: W, graphml: bool, around: Option<&[u32]>, hops: usize)
+    -> io::Result<W>
+{
+    let only = around.map(|start| graph.neighborhood(start, hops));
+    if graphml {
+        graph.write_graphml(&mut out, only.as_ref())?;
+    } else {
+        graph.write_dot(&mut out, only.as_ref())?;
+    }
+    out.flush()?;
+    Ok(out)
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    let graphml = match &opt.format[..] {
+        "dot" => false,
+        "graphml" => true,
+        format => {
+            eprintln!("unknown format {:?} (expected dot or graphml)", format);
+            process::exit(2);
+        }
+    };
+    let mut graph = RefGraph::new();
+    for input in &opt.inputs {
+        let contents = nomster::read_file(input).unwrap();
+        for err in graph.add_file(&input.to_string_lossy(), &contents) {
+            eprintln!("{}: {}", input.display(), err);
+        }
+    }
+    let around = opt.around.as_ref().map(|word| graph.find_word(word));
+    if around.is_some_and(|start| start.is_empty()) {
+        eprintln!("no entry for {:?}", opt.around.unwrap());
+        process::exit(1);
+    }
+    match opt.output {
+        Some(ref output) => {
+            let file = AtomicFile::create(output).unwrap();
+            let out = export(&graph, BufWriter::new(file), graphml, around, opt.hops).unwrap();
+            out.into_inner().unwrap().commit(Backup::None).unwrap();
+        }
+        None => {
+            let _ = export(&graph, io::stdout().lock(), graphml, around, opt.hops).unwrap();
+        }
+    }
+}
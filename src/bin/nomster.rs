@@ -0,0 +1,307 @@
+extern crate nomster;
+extern crate structopt;
+
+use nomster::{Backup, ParseOptions, TocAllocator};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "nomster", about = "Tools for the webster html dictionary")]
+enum Opt {
+    #[structopt(name = "verify", about = "Check that every entry parses and renders back to its source")]
+    Verify {
+        #[structopt(name = "FILE", help = "webster html file", parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(long = "lenient", help = "keep unknown markup instead of failing the entry")]
+        lenient: bool,
+    },
+    #[structopt(name = "patch", about = "Apply a chain of registered patches in order")]
+    Patch {
+        #[structopt(name = "INFILE", help = "webster html file", parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(name = "ID", help = "patch ids, applied in the given order", required = true)]
+        ids: Vec<String>,
+        #[structopt(short = "o", long = "output", help = "patched output (default: overwrite)",
+                    parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(long = "dry-run", help = "print a diff of each touched entry instead of writing")]
+        dry_run: bool,
+        #[structopt(long = "stat", help = "print counts of changed lines per entry instead of writing")]
+        stat: bool,
+        #[structopt(long = "backup", default_value = "none",
+                    help = "keep a copy of the replaced file: none, bak or timestamp")]
+        backup: Backup,
+        #[structopt(long = "toc-state", help = "tocid allocator state for new entries to load and update",
+                    parse(from_os_str))]
+        toc_state: Option<PathBuf>,
+        #[structopt(long = "toc-range", default_value = "30000-40000",
+                    help = "hex range of ids for new entries, when there is no saved state")]
+        toc_range: String,
+    },
+    #[structopt(name = "refs", about = "Check cross-references between entries")]
+    Refs {
+        #[structopt(name = "FILE", help = "webster html files", parse(from_os_str), required = true)]
+        inputs: Vec<PathBuf>,
+        #[structopt(long = "orphans", help = "list entries no other entry links to")]
+        orphans: bool,
+        #[structopt(long = "fix", help = "repair dangling links and self-links in place, where unambiguous")]
+        fix: bool,
+        #[structopt(long = "backup", default_value = "none",
+                    help = "keep a copy of each replaced file: none, bak or timestamp")]
+        backup: Backup,
+    },
+    #[structopt(name = "toc", about = "Check for duplicate tocids and allocate unused ones")]
+    Toc {
+        #[structopt(name = "FILE", help = "webster html files", parse(from_os_str), required = true)]
+        inputs: Vec<PathBuf>,
+        #[structopt(long = "state", help = "allocator state to load and update", parse(from_os_str))]
+        state: Option<PathBuf>,
+        #[structopt(long = "range", default_value = "30000-40000",
+                    help = "hex range of ids to allocate from, when there is no saved state")]
+        range: String,
+        #[structopt(long = "allocate", default_value = "0", help = "number of ids to allocate")]
+        allocate: usize,
+    },
+    #[structopt(name = "order", about = "Check that entries are in dictionary order")]
+    Order {
+        #[structopt(name = "FILE", help = "webster html files", parse(from_os_str), required = true)]
+        inputs: Vec<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PatchMode {
+    Write(Backup),
+    DryRun,
+    Stat,
+}
+
+fn verify(input: &Path, options: ParseOptions) -> bool {
+    let contents = nomster::read_file(input).unwrap();
+    let report = nomster::verify_roundtrip(&contents, options);
+    for err in &report.errors {
+        eprintln!("{}", err);
+    }
+    for mismatch in &report.mismatches {
+        println!("--- MBP_TOC_{:X} {} (line {})", mismatch.tocid, mismatch.word, mismatch.line);
+        print!("{}", mismatch.diff());
+    }
+    eprintln!("{} entries, {} unparsed, {} differ",
+              report.entries, report.errors.len(), report.mismatches.len());
+    report.errors.is_empty() && report.mismatches.is_empty()
+}
+
+/// The allocator saved at `state`, or a new one handing out ids from the hex `range`.
+fn load_allocator(state: Option<&Path>, range: &str) -> Option<TocAllocator> {
+    match state {
+        Some(state) if state.exists() => {
+            Some(TocAllocator::load(std::io::BufReader::new(std::fs::File::open(state).unwrap())).unwrap())
+        }
+        _ => {
+            let bounds: Vec<_> = range.splitn(2, '-').map(|hex| u32::from_str_radix(hex, 16)).collect();
+            match bounds[..] {
+                [Ok(start), Ok(end)] => Some(TocAllocator::new(std::iter::once(start..end))),
+                _ => {
+                    eprintln!("bad range {:?}, expected START-END in hex", range);
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn save_allocator(allocator: &TocAllocator, state: &Path) {
+    let mut saved = Vec::new();
+    allocator.save(&mut saved).unwrap();
+    nomster::write_file(state, &saved, Backup::None).unwrap();
+}
+
+fn patch(input: &Path, ids: &[String], output: &Path, mode: PatchMode, toc_state: Option<&Path>,
+         toc_range: &str) -> bool {
+    let mut patches = Vec::with_capacity(ids.len());
+    for id in ids {
+        match nomster::patch::find(id) {
+            Some(patch) => patches.push(patch),
+            None => {
+                eprintln!("unknown patch {:?}; known patches:", id);
+                for patch in nomster::patch::registry() {
+                    eprintln!("  {:10} {}", patch.id(), patch.description());
+                }
+                return false;
+            }
+        }
+    }
+    let mut allocator = match load_allocator(toc_state, toc_range) {
+        Some(allocator) => allocator,
+        None => return false,
+    };
+    let original = nomster::read_file(input).unwrap();
+    let mut contents = original.clone();
+    for mut patch in patches {
+        let mut log = Vec::new();
+        // each patch rescans the document it is given
+        allocator.forget_scanned();
+        if let Some(ids) = patch.toc_allocator() {
+            mem::swap(ids, &mut allocator);
+        }
+        contents = patch.apply(&contents, &mut log);
+        if let Some(ids) = patch.toc_allocator() {
+            mem::swap(ids, &mut allocator);
+        }
+        for line in &log {
+            eprintln!("{}: {}", patch.id(), line);
+        }
+    }
+    if let PatchMode::Write(backup) = mode {
+        nomster::write_file(output, contents.as_bytes(), backup).unwrap();
+        if let Some(state) = toc_state {
+            save_allocator(&allocator, state);
+        }
+        return true;
+    }
+    let diffs = nomster::patch::diff_entries(&original, &contents);
+    let (mut inserted, mut deleted) = (0, 0);
+    for entry in &diffs {
+        let header = match entry.tocid {
+            Some(tocid) => format!("MBP_TOC_{:X} {}", tocid, entry.word).trim_end().to_string(),
+            None => "(before first entry)".to_string(),
+        };
+        if mode == PatchMode::DryRun {
+            println!("--- {} (line {})", header, entry.line);
+            print!("{}", entry.diff());
+        } else {
+            let (ins, del) = entry.line_stat();
+            println!("{} | +{} -{}", header, ins, del);
+            inserted += ins;
+            deleted += del;
+        }
+    }
+    if mode == PatchMode::Stat {
+        println!("{} entries touched, {} insertions(+), {} deletions(-)",
+                 diffs.len(), inserted, deleted);
+    } else {
+        eprintln!("{} entries touched", diffs.len());
+    }
+    true
+}
+
+fn refs(inputs: &[PathBuf], list_orphans: bool, fix: bool, backup: Backup) -> bool {
+    use nomster::refs::{Link, RefGraph};
+    let contents: Vec<String> = inputs.iter().map(|input| nomster::read_file(input).unwrap()).collect();
+    let mut graph = RefGraph::new();
+    for (input, contents) in inputs.iter().zip(&contents) {
+        for err in graph.add_file(&input.to_string_lossy(), contents) {
+            eprintln!("{}: {}", input.display(), err);
+        }
+    }
+    let report = graph.report();
+    let print_link = |issue: &str, link: &Link| {
+        let target = graph.nodes.get(&link.to).map_or("", |node| &node.word[..]);
+        println!("{}:{}: MBP_TOC_{:X}: {} MBP_TOC_{:X} {:?} (target {:?})",
+                 graph.files[link.file], link.line, link.from, issue, link.to, link.text, target);
+    };
+    for link in &report.dangling {
+        print_link("dangling link to", link);
+    }
+    for link in &report.self_links {
+        print_link("self-link", link);
+    }
+    for link in &report.mismatches {
+        print_link("link text differs from", link);
+    }
+    if list_orphans {
+        for node in &report.orphans {
+            println!("{}:{}: MBP_TOC_{:X}: orphan {}",
+                     graph.files[node.file], node.line, node.tocid, node.word);
+        }
+    }
+    eprintln!("{} entries, {} links: {} dangling, {} self-links, {} mismatched, {} orphans",
+              graph.nodes.len(), graph.links.len(), report.dangling.len(), report.self_links.len(),
+              report.mismatches.len(), report.orphans.len());
+    let ok = report.dangling.is_empty() && report.self_links.is_empty() && report.mismatches.is_empty();
+    if fix {
+        for (input, contents) in inputs.iter().zip(&contents) {
+            let mut log = Vec::new();
+            let (fixed, count) = nomster::refs::fix_links(&graph, contents, &mut log);
+            for line in &log {
+                eprintln!("{}: {}", input.display(), line);
+            }
+            if count > 0 {
+                nomster::write_file(input, fixed.as_bytes(), backup).unwrap();
+            }
+            eprintln!("{}: {} links repaired", input.display(), count);
+        }
+    }
+    ok
+}
+
+fn toc(inputs: &[PathBuf], state: Option<&Path>, range: &str, allocate: usize) -> bool {
+    let mut allocator = match load_allocator(state, range) {
+        Some(allocator) => allocator,
+        None => return false,
+    };
+    let mut ok = true;
+    for input in inputs {
+        let contents = nomster::read_file(input).unwrap();
+        for tocid in allocator.scan(&contents) {
+            println!("{}: duplicate MBP_TOC_{:X}", input.display(), tocid);
+            ok = false;
+        }
+    }
+    for _ in 0..allocate {
+        match allocator.allocate() {
+            Some(tocid) => println!("MBP_TOC_{:X}", tocid),
+            None => {
+                eprintln!("no free tocid left");
+                ok = false;
+                break;
+            }
+        }
+    }
+    if let Some(state) = state {
+        save_allocator(&allocator, state);
+    }
+    ok
+}
+
+fn order(inputs: &[PathBuf]) -> bool {
+    let collator = nomster::Collator::new();
+    let mut ok = true;
+    for input in inputs {
+        let contents = nomster::read_file(input).unwrap();
+        let misordered = collator.check_order(&contents);
+        for entry in &misordered {
+            println!("{}:{}: MBP_TOC_{:X}: {:?} sorts before the preceding MBP_TOC_{:X} {:?}",
+                     input.display(), entry.line, entry.tocid, entry.word,
+                     entry.previous_tocid, entry.previous_word);
+        }
+        eprintln!("{}: {} entries out of order", input.display(), misordered.len());
+        ok &= misordered.is_empty();
+    }
+    ok
+}
+
+fn main() {
+    let ok = match Opt::from_args() {
+        Opt::Verify { input, lenient } => verify(&input, ParseOptions { lenient }),
+        Opt::Patch { input, ids, output, dry_run, stat, backup, toc_state, toc_range } => {
+            let mode = if stat {
+                PatchMode::Stat
+            } else if dry_run {
+                PatchMode::DryRun
+            } else {
+                PatchMode::Write(backup)
+            };
+            patch(&input, &ids, output.as_ref().unwrap_or(&input), mode, toc_state.as_deref(), &toc_range)
+        }
+        Opt::Refs { inputs, orphans, fix, backup } => refs(&inputs, orphans, fix, backup),
+        Opt::Toc { inputs, state, range, allocate } =>
+            toc(&inputs, state.as_deref(), &range, allocate),
+        Opt::Order { inputs } => order(&inputs),
+    };
+    if !ok {
+        process::exit(1);
+    }
+}
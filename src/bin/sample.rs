@@ -1,9 +1,7 @@
 extern crate nomster;
 
-#[macro_use]
 extern crate structopt;
 
-use nomster::Parser;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -15,9 +13,12 @@ struct Opt {
 
 fn main() {
     let opt = Opt::from_args();
-    let contents = nomster::read_file(&opt.input).unwrap();
-    let entry_iter = Parser::new(&contents);
-    for (_, entry) in entry_iter {
-        println!("{:?}", entry);
+    let entry_iter = nomster::open_file(&opt.input).unwrap();
+    for entry in entry_iter {
+        let entry = entry.unwrap();
+        match entry.parse() {
+            Ok(entry) => println!("{:?}", entry),
+            Err(err) => eprintln!("{}", err),
+        }
     }
 }
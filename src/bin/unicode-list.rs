@@ -2,7 +2,6 @@ extern crate nomster;
 
 #[macro_use]
 extern crate nom;
-#[macro_use]
 extern crate structopt;
 
 use nomster::{read_file, parser};
@@ -37,10 +36,10 @@ fn unicode_list(mut contents: &str) {
         let word = parser::strip_stress(word);
         let mut u_iter = word.chars().map(|c| c as u32).peekable();
         while let Some(u) = u_iter.next() {
-            if u >= 20 && u < 127 { continue; }
+            if (20..127).contains(&u) { continue; }
             let following = u_iter.peek().map_or(0, |&u| u);
             match non_ascii_set.entry(u) {
-                Entry::Vacant(mut entry) => {
+                Entry::Vacant(entry) => {
                     entry.insert(following);
                 }
                 Entry::Occupied(mut entry) => {
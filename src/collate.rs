@@ -0,0 +1,147 @@
+//! Dictionary order of headwords.
+//!
+//! Words are compared level by level, a level only deciding when all those before it are equal:
+//!
+//! 1. the letters and digits of their ascii folding, ignoring case, so that `"Lawgiver"`,
+//!    `"law-giver"` and `"law´giv•er"` compare equal;
+//! 2. diacritics, unaccented before accented: `"resume"` before `"résumé"`;
+//! 3. case, lowercase before uppercase: `"march"` before `"March"`;
+//! 4. hyphens, spaces and other punctuation;
+//! 5. stress and syllable marks, so that distinct words never compare equal.
+
+use ascii::{AsciiFolder, Unmapped};
+use parser::{self, strip_stress, LineStart};
+use std::cmp::Ordering;
+
+#[derive(Clone, Debug)]
+pub struct Collator {
+    folder: AsciiFolder,
+}
+
+/// The sort key of a word, comparing as the word does under the collator that made it.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CollationKey {
+    primary: String,
+    /// For each letter or digit, 0 if it is ascii, else the character.
+    secondary: Vec<u32>,
+    /// For each letter or digit, whether it is uppercase.
+    tertiary: Vec<bool>,
+    quaternary: String,
+    identical: String,
+}
+
+/// An entry that sorts before the entry preceding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Misordered {
+    pub tocid: u32,
+    pub word: String,
+    pub line: usize,
+    pub previous_tocid: u32,
+    pub previous_word: String,
+}
+
+impl Default for Collator {
+    fn default() -> Collator {
+        Collator::with_folder(AsciiFolder::new(Unmapped::Keep))
+    }
+}
+
+impl Collator {
+    pub fn new() -> Collator {
+        Collator::default()
+    }
+
+    /// A collator folding letters to ascii for the primary level with `folder`. Characters it
+    /// cannot fold are kept rather than failing.
+    pub fn with_folder(mut folder: AsciiFolder) -> Collator {
+        folder.unmapped = Unmapped::Keep;
+        Collator { folder }
+    }
+
+    /// The primary level of `word`: the lowercase letters and digits of its ascii folding.
+    pub fn primary_key(&self, word: &str) -> String {
+        self.folder.fold(word).unwrap_or_default().chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    pub fn key(&self, word: &str) -> CollationKey {
+        let unmarked = strip_stress(word);
+        let letters = unmarked.chars().filter(|c| c.is_alphanumeric());
+        CollationKey {
+            primary: self.primary_key(word),
+            secondary: letters.clone()
+                .map(|c| if c.is_ascii() { 0 } else { c.to_lowercase().next().unwrap_or(c) as u32 })
+                .collect(),
+            tertiary: letters.map(char::is_uppercase).collect(),
+            quaternary: unmarked,
+            identical: word.to_string(),
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.key(a).cmp(&self.key(b))
+    }
+
+    /// Check that the entries of `contents` are in dictionary order by their `data-ascii` words,
+    /// returning each entry whose word sorts at the primary level before that of the entry
+    /// preceding it.
+    pub fn check_order(&self, contents: &str) -> Vec<Misordered> {
+        let mut misordered = Vec::new();
+        let mut previous: Option<(u32, &str, String)> = None;
+        let mut line = LineStart::default();
+        let mut rest = contents;
+        while let Some((skipped, entry_str, next)) = parser::split_next(rest) {
+            let offset = contents.len() - rest.len();
+            line.advance(skipped, offset);
+            if let Some((tocid, word)) = parser::entry_header(entry_str) {
+                let key = self.primary_key(word);
+                if let Some((previous_tocid, previous_word, ref previous_key)) = previous {
+                    if key < *previous_key {
+                        misordered.push(Misordered {
+                            tocid,
+                            word: word.to_string(),
+                            line: line.line,
+                            previous_tocid,
+                            previous_word: previous_word.to_string(),
+                        });
+                    }
+                }
+                previous = Some((tocid, word, key));
+            }
+            line.advance(entry_str, offset + skipped.len());
+            rest = next;
+        }
+        misordered
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Collator, Misordered};
+
+    #[test]
+    fn collate_test() {
+        let collator = Collator::new();
+        let mut words = vec!["Zebra", "résumé", "Lawgiver", "aardvark", "A•bide´", "Resume", "law-giver",
+                             "resume", "Abide"];
+        words.sort_by_cached_key(|word| collator.key(word));
+        assert_eq!(words, ["aardvark", "Abide", "A•bide´", "law-giver", "Lawgiver", "resume", "Resume",
+                           "résumé", "Zebra"]);
+        assert_eq!(collator.primary_key("Zöll´ner’s Lines"), "zollnerslines");
+
+        let contents = "<div id=\"MBP_TOC_A\" data-ascii=\"Abbey\">\n<p><strong>Ab´bey</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"Abide\">\n<p><strong>A•bide´</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"Aardvark\">\n<p><strong>Aard´vark</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_D\" data-ascii=\"ab-ode\">\n<p><strong>Ab-ode</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_E\" data-ascii=\"Abode\">\n<p><strong>A•bode´</strong></p>\n</div>\n";
+        assert_eq!(collator.check_order(contents), [Misordered {
+            tocid: 0xC,
+            word: "Aardvark".into(),
+            line: 7,
+            previous_tocid: 0xB,
+            previous_word: "Abide".into(),
+        }]);
+    }
+}
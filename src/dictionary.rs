@@ -0,0 +1,172 @@
+//! Headword lookup over a whole dictionary file, parsing entries only when they are asked for.
+
+use collate::Collator;
+use parser::{self, fold_to_ascii, LineStart, ParseError, ParseOptions, TaggedEntry};
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Where an entry is in the dictionary source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+    /// The `data-ascii` word of the entry.
+    pub key: String,
+    pub tocid: u32,
+    /// Byte range of the entry in the source.
+    pub span: Range<usize>,
+    /// 1-based line the entry starts on.
+    pub line: usize,
+}
+
+pub struct Dictionary {
+    contents: String,
+    options: ParseOptions,
+    /// Sorted by key, then by position in the source.
+    index: Vec<IndexEntry>,
+    by_id: HashMap<u32, usize>,
+    unindexed: Vec<Range<usize>>,
+}
+
+/// Entries of a dictionary, parsed as they are iterated.
+pub struct Entries<'d> {
+    dict: &'d Dictionary,
+    index: ::std::slice::Iter<'d, IndexEntry>,
+}
+
+impl<'d> Iterator for Entries<'d> {
+    type Item = Result<TaggedEntry<'d>, ParseError<'d>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.index.next().map(|entry| self.dict.parse(entry))
+    }
+}
+
+impl Dictionary {
+    pub fn new(contents: String) -> Dictionary {
+        Dictionary::with_options(contents, ParseOptions::default())
+    }
+
+    pub fn with_options(contents: String, options: ParseOptions) -> Dictionary {
+        let mut index = Vec::new();
+        let mut unindexed = Vec::new();
+        let mut rest = &contents[..];
+        let mut line = LineStart::default();
+        while let Some((skipped, entry_str, next)) = parser::split_next(rest) {
+            let start = contents.len() - rest.len() + skipped.len();
+            let span = start..start + entry_str.len();
+            line.advance(skipped, start - skipped.len());
+            match parser::entry_header(entry_str) {
+                Some((tocid, word)) =>
+                    index.push(IndexEntry { key: word.to_string(), tocid, span, line: line.line }),
+                None => unindexed.push(span),
+            }
+            line.advance(entry_str, start);
+            rest = next;
+        }
+        index.sort_by(|a, b| a.key.cmp(&b.key).then(a.span.start.cmp(&b.span.start)));
+        let mut by_id = HashMap::with_capacity(index.len());
+        for (i, entry) in index.iter().enumerate() {
+            by_id.entry(entry.tocid).or_insert(i);
+        }
+        Dictionary { contents, options, index, by_id, unindexed }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Dictionary> {
+        ::read_file(path).map(Dictionary::new)
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// The index, sorted by key.
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    /// Byte ranges of the entries left out of the index because their `<div>` is malformed.
+    pub fn unindexed(&self) -> &[Range<usize>] {
+        &self.unindexed
+    }
+
+    /// The index in dictionary order, as `collator` sorts the keys.
+    pub fn collated(&self, collator: &Collator) -> Vec<&IndexEntry> {
+        let mut entries: Vec<&IndexEntry> = self.index.iter().collect();
+        entries.sort_by_cached_key(|entry| collator.key(&entry.key));
+        entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn parse(&self, entry: &IndexEntry) -> Result<TaggedEntry<'_>, ParseError<'_>> {
+        let line_start = self.contents[..entry.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let from = LineStart { line: entry.line, offset: line_start };
+        parser::parse_entry_str(&self.contents, &self.contents[entry.span.clone()], from, self.options)
+    }
+
+    fn entries(&self, range: Range<usize>) -> Entries<'_> {
+        Entries { dict: self, index: self.index[range].iter() }
+    }
+
+    /// All entries whose headword is `word`, as compared by `fold_to_ascii`.
+    pub fn get(&self, word: &str) -> Entries<'_> {
+        let key = fold_to_ascii(word);
+        let start = self.index.partition_point(|entry| entry.key < key);
+        let end = start + self.index[start..].partition_point(|entry| entry.key == key);
+        self.entries(start..end)
+    }
+
+    /// The entry with the given tocid; the first one if there are several.
+    pub fn get_by_id(&self, tocid: u32) -> Option<Result<TaggedEntry<'_>, ParseError<'_>>> {
+        self.by_id.get(&tocid).map(|&i| self.parse(&self.index[i]))
+    }
+
+    /// All entries whose `fold_to_ascii` headword starts with `prefix`, in key order.
+    pub fn range(&self, prefix: &str) -> Entries<'_> {
+        let prefix = fold_to_ascii(prefix);
+        let start = self.index.partition_point(|entry| entry.key < prefix);
+        let end = start + self.index[start..].partition_point(|entry| entry.key.starts_with(&prefix));
+        self.entries(start..end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Dictionary;
+    use collate::Collator;
+
+    #[test]
+    fn lookup_test() {
+        let contents = "<html>\n<div id=\"MBP_TOC_B\" data-ascii=\"Abide\">\n<p><strong>A•bide´</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_A\" data-ascii=\"Abbey\">\n<p><strong>Ab´bey</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"Abide\">\n<p><u>x</u></p>\n</div>\n\
+                        <div id=\"MBP_TOC_\">\n</div>\n\
+                        <div id=\"MBP_TOC_D\" data-ascii=\"Bide\">\n<p><strong>Bide</strong></p>\n</div>\n</html>\n";
+        let dict = Dictionary::new(contents.to_string());
+        assert_eq!(dict.len(), 4);
+        assert_eq!(dict.unindexed().len(), 1);
+        let keys: Vec<_> = dict.index().iter().map(|entry| (&entry.key[..], entry.tocid)).collect();
+        assert_eq!(keys, [("Abbey", 0xA), ("Abide", 0xB), ("Abide", 0xC), ("Bide", 0xD)]);
+
+        let abide: Vec<_> = dict.get("A•bide´").collect();
+        assert_eq!(abide.len(), 2);
+        assert_eq!(abide[0].as_ref().unwrap().tocid, 0xB);
+        assert_eq!(abide[1].as_ref().unwrap_err().tocid, Some(0xC));
+        assert_eq!(abide[1].as_ref().map_err(|err| (err.line, err.column)).unwrap_err(), (9, 4));
+        assert_eq!(dict.get("Abid").count(), 0);
+        assert_eq!(dict.get_by_id(0xD).unwrap().unwrap().word, "Bide");
+        assert!(dict.get_by_id(0xE).is_none());
+        assert_eq!(dict.range("Ab").count(), 3);
+        assert_eq!(dict.range("").count(), 4);
+        assert_eq!(dict.range("C").count(), 0);
+        let collated: Vec<_> = dict.collated(&Collator::new()).iter().map(|entry| entry.tocid).collect();
+        assert_eq!(collated, [0xA, 0xB, 0xC, 0xD]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Line-by-line edit script turning `old` into `new`, by longest common subsequence.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    // only the part between the common prefix and suffix needs the quadratic table
+    let prefix = old.iter().zip(new).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b).count();
+    let mut script: Vec<_> = old[..prefix].iter().map(|&line| (Op::Equal, line)).collect();
+    script.extend(lcs_edits(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]));
+    script.extend(old[old.len() - suffix..].iter().map(|&line| (Op::Equal, line)));
+    script
+}
+
+fn lcs_edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![0usize; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if old[i] == new[j] {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut script = Vec::with_capacity(n.max(m));
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            script.push((Op::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+            script.push((Op::Delete, old[i]));
+            i += 1;
+        } else {
+            script.push((Op::Insert, new[j]));
+            j += 1;
+        }
+    }
+    script
+}
+
+/// Unified diff of `old` against `new`, with `context` unchanged lines around each change.
+///
+/// Returns an empty string if both are equal.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> String {
+    let script = edits(&lines(old), &lines(new));
+    let changed: Vec<usize> = (0..script.len()).filter(|&k| script[k].0 != Op::Equal).collect();
+    let mut out = String::new();
+    let mut k = 0;
+    while k < changed.len() {
+        // extend the hunk while the next change is within reach of its context
+        let start = changed[k].saturating_sub(context);
+        let mut last = changed[k];
+        k += 1;
+        while k < changed.len() && changed[k] <= last + 2 * context + 1 {
+            last = changed[k];
+            k += 1;
+        }
+        let end = (last + context + 1).min(script.len());
+        let old_start = script[..start].iter().filter(|e| e.0 != Op::Insert).count();
+        let new_start = script[..start].iter().filter(|e| e.0 != Op::Delete).count();
+        let old_len = script[start..end].iter().filter(|e| e.0 != Op::Insert).count();
+        let new_len = script[start..end].iter().filter(|e| e.0 != Op::Delete).count();
+        writeln!(out, "@@ -{},{} +{},{} @@", old_start + 1, old_len, new_start + 1, new_len).unwrap();
+        for &(op, line) in &script[start..end] {
+            out.push(match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            });
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Number of lines inserted and deleted to turn `old` into `new`.
+pub fn line_stat(old: &str, new: &str) -> (usize, usize) {
+    let script = edits(&lines(old), &lines(new));
+    let inserted = script.iter().filter(|e| e.0 == Op::Insert).count();
+    let deleted = script.iter().filter(|e| e.0 == Op::Delete).count();
+    (inserted, deleted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{line_stat, unified_diff};
+
+    #[test]
+    fn unified_diff_test() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", 3), "");
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\nten";
+        assert_eq!(unified_diff(old, new, 1),
+                   "@@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n\
+                    @@ -9,1 +9,2 @@\n 9\n+ten\n\\ No newline at end of file\n");
+        assert_eq!(unified_diff(old, new, 2).matches("@@ -").count(), 2);
+        assert_eq!(unified_diff(old, new, 3).matches("@@ -").count(), 1);
+        assert_eq!(line_stat(old, new), (2, 1));
+    }
+}
@@ -0,0 +1,244 @@
+//! Headword lookup forgiving of case, diacritics, stress marks and misspellings.
+//!
+//! Headwords and queries are compared by their `key`: the lowercase alphanumerics of their
+//! `fold_to_ascii` translation, so that `"ameba"` and `"A•mœ´ba"` compare as `"ameba"` and
+//! `"amoeba"`. A query first matches headwords, or single words of multiword headwords, with an
+//! equal key; failing that, keys within an edit distance; failing that, keys that sound alike.
+
+use parser::fold_to_ascii;
+use std::cmp;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchOptions {
+    /// Greatest Damerau-Levenshtein distance of a suggestion; 0 disables suggestions.
+    pub max_distance: usize,
+    /// Fall back to headwords with the same Soundex code.
+    pub phonetic: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions { max_distance: 2, phonetic: true }
+    }
+}
+
+/// How a headword matched a query, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The whole headword.
+    Exact,
+    /// A word of a multiword headword, like `"Zollner"` in `"Zöll´ner’s Lines"`.
+    Word,
+    /// The headword, or one of its words, within this edit distance.
+    Edit(usize),
+    /// The headword, or one of its words, has the same Soundex code.
+    Phonetic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub tocid: u32,
+    pub headword: String,
+    pub kind: MatchKind,
+}
+
+struct Headword {
+    tocid: u32,
+    text: String,
+    key: String,
+    /// Keys of the words of a multiword headword; empty for a single word.
+    words: Vec<String>,
+}
+
+impl Headword {
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        Some(&self.key).into_iter().chain(&self.words)
+    }
+}
+
+pub struct Matcher {
+    headwords: Vec<Headword>,
+    options: MatchOptions,
+}
+
+/// The lowercase alphanumerics of the ascii translation of `word`.
+pub fn key(word: &str) -> String {
+    fold_to_ascii(word).chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The optimal string alignment distance between `a` and `b`: the number of insertions,
+/// deletions, substitutions and transpositions of adjacent characters turning one into the other.
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows i - 2, i - 1 and i of the distance matrix
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = cmp::min(cmp::min(previous[j] + 1, current[j - 1] + 1), previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = cmp::min(current[j], before[j - 2] + 1);
+            }
+        }
+        before = previous;
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The American Soundex code of the ascii letters of `word`, like `"A514"` for `"Amoeba"`.
+pub fn soundex(word: &str) -> Option<String> {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let mut letters = word.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_lowercase());
+    let first = letters.next()?;
+    let mut code = first.to_ascii_uppercase().to_string();
+    let mut last = digit(first);
+    for c in letters {
+        match digit(c) {
+            Some(d) if last != Some(d) => {
+                code.push(d);
+                if code.len() == 4 {
+                    break;
+                }
+                last = Some(d);
+            }
+            Some(_) => (),
+            // h and w do not separate letters of the same code; vowels do
+            None if c == 'h' || c == 'w' => (),
+            None => last = None,
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+impl Matcher {
+    /// A matcher over `(tocid, headword)` pairs, like those of `Dictionary::index`.
+    pub fn new<I, S>(headwords: I) -> Matcher
+        where I: IntoIterator<Item = (u32, S)>, S: AsRef<str>
+    {
+        Matcher::with_options(headwords, MatchOptions::default())
+    }
+
+    pub fn with_options<I, S>(headwords: I, options: MatchOptions) -> Matcher
+        where I: IntoIterator<Item = (u32, S)>, S: AsRef<str>
+    {
+        let headwords = headwords.into_iter().map(|(tocid, text)| {
+            let text = text.as_ref();
+            let words: Vec<String> = text.split(|c: char| c.is_whitespace() || c == '-' || c == '\'' || c == '’')
+                .map(key)
+                .filter(|word| !word.is_empty())
+                .collect();
+            Headword {
+                tocid,
+                text: text.to_string(),
+                key: key(text),
+                words: if words.len() > 1 { words } else { Vec::new() },
+            }
+        }).collect();
+        Matcher { headwords, options }
+    }
+
+    /// Headwords matching `query`, best first. Suggestions by edit distance are only made when
+    /// nothing matches exactly, and phonetic ones when there are no suggestions either.
+    pub fn lookup(&self, query: &str) -> Vec<Match> {
+        let query = key(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches = self.collect(|headword| {
+            if headword.key == query {
+                Some(MatchKind::Exact)
+            } else if headword.words.contains(&query) {
+                Some(MatchKind::Word)
+            } else {
+                None
+            }
+        });
+        if matches.is_empty() && self.options.max_distance > 0 {
+            let max = self.options.max_distance;
+            let query_len = query.chars().count();
+            matches = self.collect(|headword| {
+                headword.keys()
+                    .filter(|key| (key.chars().count() as isize - query_len as isize).unsigned_abs() <= max)
+                    .map(|key| damerau_levenshtein(&query, key))
+                    .filter(|&distance| distance <= max)
+                    .min()
+                    .map(MatchKind::Edit)
+            });
+        }
+        if matches.is_empty() && self.options.phonetic {
+            if let Some(code) = soundex(&query) {
+                matches = self.collect(|headword| {
+                    if headword.keys().any(|key| soundex(key).as_ref() == Some(&code)) {
+                        Some(MatchKind::Phonetic)
+                    } else {
+                        None
+                    }
+                });
+            }
+        }
+        matches
+    }
+
+    fn collect<F: Fn(&Headword) -> Option<MatchKind>>(&self, kind: F) -> Vec<Match> {
+        let mut matches: Vec<(MatchKind, &Headword)> = self.headwords.iter()
+            .filter_map(|headword| kind(headword).map(|kind| (kind, headword)))
+            .collect();
+        matches.sort_by(|a, b| a.0.cmp(&b.0)
+                        .then_with(|| a.1.key.cmp(&b.1.key))
+                        .then(a.1.tocid.cmp(&b.1.tocid)));
+        matches.into_iter()
+            .map(|(kind, headword)| Match { tocid: headword.tocid, headword: headword.text.clone(), kind })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{damerau_levenshtein, soundex, MatchKind, MatchOptions, Matcher};
+
+    #[test]
+    fn lookup_test() {
+        let headwords = [(1, "A•mœ´ba"), (2, "Zöll´ner’s Lines"), (3, "Am´ble"), (4, "Ameer"), (5, "Robert")];
+        let matcher = Matcher::new(headwords.iter().cloned());
+        let lookup = |query| matcher.lookup(query).into_iter().map(|m| (m.tocid, m.kind)).collect::<Vec<_>>();
+        assert_eq!(lookup("AMOEBA"), [(1, MatchKind::Exact)]);
+        assert_eq!(lookup("ameba"), [(1, MatchKind::Edit(1)), (4, MatchKind::Edit(2))]);
+        assert_eq!(lookup("Zollner"), [(2, MatchKind::Word)]);
+        assert_eq!(lookup("zöllner's lines"), [(2, MatchKind::Exact)]);
+        assert_eq!(lookup("amebe"), [(3, MatchKind::Edit(2)), (4, MatchKind::Edit(2)), (1, MatchKind::Edit(2))]);
+        assert_eq!(lookup("Roopurt"), [(5, MatchKind::Phonetic)]);
+        assert!(lookup("xyzzy").is_empty());
+
+        let strict = Matcher::with_options(headwords.iter().cloned(), MatchOptions { max_distance: 0, phonetic: false });
+        assert!(strict.lookup("ameba").is_empty());
+
+        assert_eq!(damerau_levenshtein("amoeba", "amoeab"), 1);
+        assert_eq!(damerau_levenshtein("", "abc"), 3);
+        assert_eq!(soundex("Ashcraft").unwrap(), "A261");
+        assert_eq!(soundex("Tymczak").unwrap(), "T522");
+        assert_eq!(soundex("Pfister").unwrap(), "P236");
+        assert_eq!(soundex("Lee").unwrap(), "L000");
+    }
+}
@@ -3,7 +3,6 @@ extern crate nomster;
 #[macro_use]
 extern crate nom;
 
-#[macro_use]
 extern crate structopt;
 
 use std::path::PathBuf;
@@ -1,11 +1,11 @@
 extern crate nomster;
 
-#[macro_use]
 extern crate structopt;
 
 #[macro_use]
 extern crate lazy_static;
 
+use nomster::Backup;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -15,6 +15,9 @@ struct Opt {
     input: PathBuf,
     #[structopt(name = "OUTFILE", help = "output path", parse(from_os_str))]
     output: PathBuf,
+    #[structopt(long = "backup", default_value = "none",
+                help = "keep a copy of the replaced file: none, bak or timestamp")]
+    backup: Backup,
 }
 
 lazy_static! {
@@ -101,14 +104,13 @@ fn to_unicode(contents: &str) -> String {
     contents
 }
 
-fn write_decoded(contents: &str, output: &PathBuf) {
-    use std::io::Write;
+fn write_decoded(contents: &str, output: &PathBuf, backup: Backup) {
     let contents = to_unicode(contents);
-    std::fs::File::create(output).unwrap().write_all(contents.as_bytes()).unwrap();
+    nomster::write_file(output, contents.as_bytes(), backup).unwrap();
 }
 
 fn main() {
     let opt = Opt::from_args();
     let contents = nomster::read_file(&opt.input).unwrap();
-    write_decoded(&contents, &opt.output);
+    write_decoded(&contents, &opt.output, opt.backup);
 }
@@ -0,0 +1,246 @@
+//! Grammatical abbreviations used in the dictionary, like `v. t.`, `imp.` or `Obs.`
+
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LabelKind {
+    PartOfSpeech,
+    Inflection,
+    Usage,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GrammarLabel {
+    // parts of speech
+    Noun,
+    Adjective,
+    Adverb,
+    Verb,
+    VerbTransitive,
+    VerbIntransitive,
+    VerbalNoun,
+    ParticipialAdjective,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Interjection,
+    Article,
+    // inflections
+    Imperfect,
+    PastParticiple,
+    PresentParticiple,
+    Present,
+    Preterit,
+    Infinitive,
+    Indicative,
+    Subjunctive,
+    Imperative,
+    Passive,
+    Singular,
+    Plural,
+    Comparative,
+    Superlative,
+    Nominative,
+    Objective,
+    Possessive,
+    Genitive,
+    Dative,
+    Accusative,
+    FirstPerson,
+    SecondPerson,
+    ThirdPerson,
+    Masculine,
+    Feminine,
+    Neuter,
+    Diminutive,
+    // usage
+    Obsolete,
+    Archaic,
+    Rare,
+    Colloquial,
+    Provincial,
+    Slang,
+    Poetic,
+}
+
+use self::GrammarLabel::*;
+
+/// Abbreviations of each label, canonical first, and its expansion. Expansions parse too.
+const LABELS: &[(GrammarLabel, &[&str], &str)] = &[
+    (Noun, &["n."], "noun"),
+    (Adjective, &["a.", "adj."], "adjective"),
+    (Adverb, &["adv."], "adverb"),
+    (Verb, &["v."], "verb"),
+    (VerbTransitive, &["v. t."], "transitive verb"),
+    (VerbIntransitive, &["v. i."], "intransitive verb"),
+    (VerbalNoun, &["vb. n."], "verbal noun"),
+    (ParticipialAdjective, &["p. a."], "participial adjective"),
+    (Pronoun, &["pron."], "pronoun"),
+    (Preposition, &["prep."], "preposition"),
+    (Conjunction, &["conj."], "conjunction"),
+    (Interjection, &["interj."], "interjection"),
+    (Article, &["art."], "article"),
+    (Imperfect, &["imp."], "imperfect"),
+    (PastParticiple, &["p. p."], "past participle"),
+    (PresentParticiple, &["p. pr."], "present participle"),
+    (Present, &["pres."], "present"),
+    (Preterit, &["pret."], "preterit"),
+    (Infinitive, &["inf."], "infinitive"),
+    (Indicative, &["indic.", "ind."], "indicative"),
+    (Subjunctive, &["subj."], "subjunctive"),
+    (Imperative, &["imper."], "imperative"),
+    (Passive, &["pass."], "passive"),
+    (Singular, &["sing."], "singular"),
+    (Plural, &["pl."], "plural"),
+    (Comparative, &["compar."], "comparative"),
+    (Superlative, &["superl."], "superlative"),
+    (Nominative, &["nom."], "nominative"),
+    (Objective, &["obj."], "objective"),
+    (Possessive, &["poss."], "possessive"),
+    (Genitive, &["gen."], "genitive"),
+    (Dative, &["dat."], "dative"),
+    (Accusative, &["acc."], "accusative"),
+    (FirstPerson, &["1st pers."], "first person"),
+    (SecondPerson, &["2d pers."], "second person"),
+    (ThirdPerson, &["3d pers."], "third person"),
+    (Masculine, &["masc."], "masculine"),
+    (Feminine, &["fem."], "feminine"),
+    (Neuter, &["neut."], "neuter"),
+    (Diminutive, &["dim."], "diminutive"),
+    (Obsolete, &["Obs."], "obsolete"),
+    (Archaic, &["Archaic"], "archaic"),
+    (Rare, &["R."], "rare"),
+    (Colloquial, &["Colloq."], "colloquial"),
+    (Provincial, &["Prov. Eng.", "Prov."], "provincial"),
+    (Slang, &["Slang"], "slang"),
+    (Poetic, &["Poetic"], "poetic"),
+];
+
+/// Whether `text` starts with `prefix`, ignoring the case of the first letter, and the match
+/// ends at a word boundary.
+fn strip_label<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut text_chars = text.chars();
+    let mut prefix_chars = prefix.chars();
+    let first = prefix_chars.next()?;
+    if !text_chars.next()?.to_lowercase().eq(first.to_lowercase()) {
+        return None;
+    }
+    let rest = text_chars.as_str().strip_prefix(prefix_chars.as_str())?;
+    match rest.chars().next() {
+        Some(c) if c.is_alphanumeric() => None,
+        _ => Some(rest),
+    }
+}
+
+/// Skip the whitespace, commas, semicolons and conjunctions between labels.
+fn skip_separators(mut text: &str) -> &str {
+    loop {
+        let trimmed = text.trim_start_matches(|c: char| c.is_whitespace() || c == ',' || c == ';');
+        text = match ["&amp;", "&", "or ", "and "].iter().find_map(|sep| trimmed.strip_prefix(sep)) {
+            Some(rest) => rest,
+            None => return trimmed,
+        };
+    }
+}
+
+impl GrammarLabel {
+    pub fn kind(self) -> LabelKind {
+        match self {
+            Noun | Adjective | Adverb | Verb | VerbTransitive | VerbIntransitive | VerbalNoun |
+            ParticipialAdjective | Pronoun | Preposition | Conjunction | Interjection | Article =>
+                LabelKind::PartOfSpeech,
+            Obsolete | Archaic | Rare | Colloquial | Provincial | Slang | Poetic => LabelKind::Usage,
+            _ => LabelKind::Inflection,
+        }
+    }
+
+    fn entry(self) -> &'static (GrammarLabel, &'static [&'static str], &'static str) {
+        LABELS.iter().find(|entry| entry.0 == self).unwrap()
+    }
+
+    /// The abbreviation used in the dictionary, like `"v. t."`.
+    pub fn abbreviation(self) -> &'static str {
+        self.entry().1[0]
+    }
+
+    /// The label spelled out, like `"transitive verb"`.
+    pub fn expansion(self) -> &'static str {
+        self.entry().2
+    }
+
+    /// Parse a single label at the start of `text`, returning it and the rest of `text`.
+    fn parse_one(text: &str, previous: Option<GrammarLabel>) -> Option<(GrammarLabel, &str)> {
+        // "v. t. & i." abbreviates "v. t. & v. i."
+        if let Some(VerbTransitive) | Some(VerbIntransitive) = previous {
+            if let Some(rest) = strip_label(text, "t.") {
+                return Some((VerbTransitive, rest));
+            }
+            if let Some(rest) = strip_label(text, "i.") {
+                return Some((VerbIntransitive, rest));
+            }
+        }
+        let mut best: Option<(GrammarLabel, &str)> = None;
+        for &(label, abbreviations, expansion) in LABELS {
+            for form in abbreviations.iter().chain(Some(&expansion)) {
+                if let Some(rest) = strip_label(text, form) {
+                    if best.is_none_or(|(_, best_rest)| rest.len() < best_rest.len()) {
+                        best = Some((label, rest));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Parse the labels at the start of `text`, like `"v. t. & i."` in `"v. t. & i. To abide."`,
+    /// returning them and the rest of `text`.
+    pub fn parse_prefix(text: &str) -> (Vec<GrammarLabel>, &str) {
+        let mut labels = Vec::new();
+        let mut rest = text.trim_start();
+        while let Some((label, after)) =
+            GrammarLabel::parse_one(skip_separators(rest), labels.last().cloned())
+        {
+            labels.push(label);
+            rest = after;
+        }
+        (labels, rest)
+    }
+
+    /// Parse `text` as a sequence of labels, failing unless all of it is consumed.
+    pub fn parse(text: &str) -> Option<Vec<GrammarLabel>> {
+        match GrammarLabel::parse_prefix(text) {
+            (ref labels, rest) if labels.is_empty() || !skip_separators(rest).is_empty() => None,
+            (labels, _) => Some(labels),
+        }
+    }
+}
+
+impl Display for GrammarLabel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.abbreviation())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GrammarLabel::*;
+    use super::{GrammarLabel, LabelKind};
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(GrammarLabel::parse("v. t. & i."), Some(vec![VerbTransitive, VerbIntransitive]));
+        assert_eq!(GrammarLabel::parse("imp. &amp; p. p."), Some(vec![Imperfect, PastParticiple]));
+        assert_eq!(GrammarLabel::parse("3d pers. sing. pres."), Some(vec![ThirdPerson, Singular, Present]));
+        assert_eq!(GrammarLabel::parse("Obs."), Some(vec![Obsolete]));
+        assert_eq!(GrammarLabel::parse("obsolete"), Some(vec![Obsolete]));
+        assert_eq!(GrammarLabel::parse("Obsolescent"), None);
+        assert_eq!(GrammarLabel::parse("pl. of Abode"), None);
+        assert_eq!(GrammarLabel::parse_prefix("pl. of Abode"), (vec![Plural], " of Abode"));
+        assert_eq!(GrammarLabel::parse_prefix("p. pr."), (vec![PresentParticiple], ""));
+        assert_eq!(GrammarLabel::parse_prefix("i. e."), (vec![], "i. e."));
+        assert_eq!(VerbTransitive.abbreviation(), "v. t.");
+        assert_eq!(VerbTransitive.expansion(), "transitive verb");
+        assert_eq!(Noun.kind(), LabelKind::PartOfSpeech);
+        assert_eq!(Obsolete.kind(), LabelKind::Usage);
+    }
+}
@@ -0,0 +1,295 @@
+//! Full-text search over the definitions and quotations of entries.
+//!
+//! The `Plain`, `Emph` and `Bold` text of an entry and its blockquotes, with their authors, is
+//! split into lowercase alphanumeric terms whose positions are kept, so that queries can ask for
+//! exact phrases. Matches are ranked by BM25.
+
+use parser::{strip_stress, ParaTag, SimpleTag, TaggedEntry};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use visit::{walk_simple_tags, Visit};
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Doc {
+    tocid: u32,
+    /// Number of terms in the entry.
+    len: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Posting {
+    doc: u32,
+    positions: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Index {
+    docs: Vec<Doc>,
+    postings: BTreeMap<String, Vec<Posting>>,
+    total_len: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub tocid: u32,
+    pub score: f64,
+}
+
+/// Split `text` into lowercase terms, ignoring stress and syllable marks.
+pub fn tokenize(text: &str) -> Vec<String> {
+    strip_stress(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+/// Collects the terms of an entry. Positions skip one between blocks so phrases do not span them.
+#[derive(Default)]
+struct Terms {
+    position: u32,
+    len: u32,
+    terms: HashMap<String, Vec<u32>>,
+}
+
+impl Terms {
+    fn push(&mut self, text: &str) {
+        for term in tokenize(text) {
+            self.terms.entry(term).or_default().push(self.position);
+            self.position += 1;
+            self.len += 1;
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.position += 1;
+    }
+}
+
+impl Visit for Terms {
+    fn visit_blockquote(&mut self, tags: &[SimpleTag], author: Option<&str>) {
+        walk_simple_tags(self, tags);
+        self.end_block();
+        if let Some(author) = author {
+            self.push(author);
+            self.end_block();
+        }
+    }
+
+    fn visit_para(&mut self, tags: &[ParaTag]) {
+        for tag in tags {
+            self.visit_para_tag(tag);
+        }
+        self.end_block();
+    }
+
+    fn visit_bold(&mut self, text: &str) {
+        self.push(text);
+    }
+
+    fn visit_emph(&mut self, text: &str) {
+        self.push(text);
+    }
+
+    fn visit_plain(&mut self, text: &str) {
+        self.push(text);
+    }
+}
+
+fn invalid_data(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("bad index line {:?}", line))
+}
+
+/// Parse the `DOC:POS,POS,...` fields of a `term` line.
+fn parse_posting(field: &str) -> Option<Posting> {
+    let colon = field.find(':')?;
+    let doc = field[..colon].parse().ok()?;
+    let positions = field[colon + 1..].split(',').map(|pos| pos.parse().ok()).collect::<Option<_>>()?;
+    Some(Posting { doc, positions })
+}
+
+impl Index {
+    pub fn new() -> Index {
+        Index::default()
+    }
+
+    /// Number of entries indexed.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    pub fn add(&mut self, entry: &TaggedEntry) {
+        let mut terms = Terms::default();
+        terms.visit_entry(entry);
+        let doc = self.docs.len() as u32;
+        self.docs.push(Doc { tocid: entry.tocid, len: terms.len });
+        self.total_len += u64::from(terms.len);
+        for (term, positions) in terms.terms {
+            self.postings.entry(term).or_default().push(Posting { doc, positions });
+        }
+    }
+
+    /// Number of occurrences of the phrase `terms` in each entry containing it.
+    fn matches(&self, terms: &[String]) -> BTreeMap<u32, u32> {
+        let mut matches = BTreeMap::new();
+        let first = match terms.first().and_then(|term| self.postings.get(term)) {
+            Some(postings) => postings,
+            None => return matches,
+        };
+        for posting in first {
+            let mut starts = posting.positions.clone();
+            for (offset, term) in terms.iter().enumerate().skip(1) {
+                let positions = self.postings.get(term)
+                    .and_then(|postings| {
+                        // postings are in doc order, as docs are numbered as they are added
+                        postings.binary_search_by_key(&posting.doc, |p| p.doc).ok().map(|i| &postings[i])
+                    })
+                    .map_or(&[][..], |p| &p.positions[..]);
+                starts.retain(|start| positions.binary_search(&(start + offset as u32)).is_ok());
+            }
+            if !starts.is_empty() {
+                matches.insert(posting.doc, starts.len() as u32);
+            }
+        }
+        matches
+    }
+
+    /// Search for entries containing every word and every `"quoted phrase"` of `query`, best
+    /// matches first.
+    pub fn search(&self, query: &str) -> Vec<Hit> {
+        let clauses: Vec<Vec<String>> = query.split('"').enumerate()
+            .flat_map(|(i, part)| if i % 2 == 1 {
+                vec![tokenize(part)]
+            } else {
+                tokenize(part).into_iter().map(|term| vec![term]).collect()
+            })
+            .filter(|clause| !clause.is_empty())
+            .collect();
+        if clauses.is_empty() || self.docs.is_empty() {
+            return Vec::new();
+        }
+
+        let count = self.docs.len() as f64;
+        let avg_len = self.total_len as f64 / count;
+        let mut scores: Option<BTreeMap<u32, f64>> = None;
+        for clause in &clauses {
+            let matches = self.matches(clause);
+            let found = matches.len() as f64;
+            let idf = ((count - found + 0.5) / (found + 0.5) + 1.0).ln();
+            let clause_scores = matches.into_iter().map(|(doc, freq)| {
+                let freq = f64::from(freq);
+                let len = f64::from(self.docs[doc as usize].len);
+                let norm = K1 * (1.0 - B + B * len / avg_len.max(1.0));
+                (doc, idf * freq * (K1 + 1.0) / (freq + norm))
+            });
+            scores = Some(match scores {
+                None => clause_scores.collect(),
+                Some(scores) => clause_scores
+                    .filter_map(|(doc, score)| scores.get(&doc).map(|total| (doc, total + score)))
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<Hit> = scores.unwrap_or_default().into_iter()
+            .map(|(doc, score)| Hit { tocid: self.docs[doc as usize].tocid, score })
+            .collect();
+        hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap().then(a.tocid.cmp(&b.tocid)));
+        hits
+    }
+
+    /// Write the index as lines of `doc TOCID LEN`, in hex and decimal, followed by lines of
+    /// `term TERM DOC:POS,POS...`.
+    pub fn save<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for doc in &self.docs {
+            writeln!(out, "doc {:X} {}", doc.tocid, doc.len)?;
+        }
+        for (term, postings) in &self.postings {
+            write!(out, "term {}", term)?;
+            for posting in postings {
+                write!(out, " {}:", posting.doc)?;
+                for (i, pos) in posting.positions.iter().enumerate() {
+                    write!(out, "{}{}", if i == 0 { "" } else { "," }, pos)?;
+                }
+            }
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+
+    /// Read an index written by `save`.
+    pub fn load<R: BufRead>(input: R) -> io::Result<Index> {
+        let mut index = Index::default();
+        for line in input.lines() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                None => (),
+                Some("doc") => {
+                    let tocid = fields.next().and_then(|tocid| u32::from_str_radix(tocid, 16).ok());
+                    let len = fields.next().and_then(|len| len.parse().ok());
+                    match (tocid, len, fields.next()) {
+                        (Some(tocid), Some(len), None) => {
+                            index.docs.push(Doc { tocid, len });
+                            index.total_len += u64::from(len);
+                        }
+                        _ => return Err(invalid_data(&line)),
+                    }
+                }
+                Some("term") => {
+                    let term = fields.next().ok_or_else(|| invalid_data(&line))?;
+                    let postings = fields.map(parse_posting).collect::<Option<Vec<_>>>()
+                        .filter(|postings| postings.iter().all(|p| (p.doc as usize) < index.docs.len()))
+                        .filter(|postings| postings.windows(2).all(|pair| pair[0].doc < pair[1].doc))
+                        .ok_or_else(|| invalid_data(&line))?;
+                    index.postings.insert(term.to_string(), postings);
+                }
+                Some(_) => return Err(invalid_data(&line)),
+            }
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tokenize, Index};
+    use parser::Parser;
+
+    #[test]
+    fn search_test() {
+        let contents = "<div id=\"MBP_TOC_A\" data-ascii=\"Phlogiston\">\n<p><strong>Phlo•gis´ton</strong>, \
+                        <i>n.</i> The hypothetical principle of fire.</p>\n<blockquote>\n\
+                        <p>Of fire and phlogiston.</p>\n\u{2015}<i>Shak.</i></blockquote>\n</div>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"Fire\">\n<p><strong>Fire</strong>, <i>n.</i> \
+                        The principle of heat. Fire, fire, <b>fire</b>!</p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"Heat\">\n<p><strong>Heat</strong>, <i>n.</i> \
+                        Of fire.</p>\n<p>Principle.</p>\n</div>\n";
+        let mut index = Index::new();
+        for (_, entry) in Parser::new(contents) {
+            index.add(&entry.unwrap());
+        }
+        assert_eq!(tokenize("Phlo•gis´ton, <b>"), ["phlogiston", "b"]);
+        let tocids = |query| index.search(query).iter().map(|hit| hit.tocid).collect::<Vec<_>>();
+        assert_eq!(tocids("phlogiston"), [0xA]);
+        assert_eq!(tocids("Shak"), [0xA]);
+        assert_eq!(tocids("fire"), [0xB, 0xC, 0xA]);
+        assert_eq!(tocids("\"principle of\""), [0xB, 0xA]);
+        assert_eq!(tocids("\"of fire\" principle"), [0xC, 0xA]);
+        assert!(tocids("\"fire principle\"").is_empty());
+        assert!(tocids("water").is_empty());
+
+        let mut saved = Vec::new();
+        index.save(&mut saved).unwrap();
+        let loaded = Index::load(&saved[..]).unwrap();
+        assert_eq!(loaded, index);
+        assert!(Index::load(&b"term fire 0:1\n"[..]).is_err());
+        assert!(Index::load(&b"doc A 1\ndoc B 1\nterm fire 1:0 0:0\n"[..]).is_err());
+    }
+}
@@ -1,16 +1,54 @@
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "fst")]
+extern crate fst;
+#[cfg(feature = "memmap2")]
+extern crate memmap2;
+#[cfg(feature = "regex-automata")]
+extern crate regex_automata;
+#[cfg(feature = "serde")]
+extern crate serde;
+extern crate unicode_normalization;
 
-use std::io::{Error, Read};
+use std::io::{BufReader, Error, Read};
 use std::fs::File;
 use std::path::Path;
 
+pub mod ascii;
+#[cfg(feature = "autocomplete")]
+pub mod autocomplete;
+pub mod collate;
+pub mod dictionary;
+pub mod diff;
+pub mod fuzzy;
+pub mod grammar;
+pub mod index;
+pub mod output;
 pub mod parser;
+pub mod patch;
+pub mod refs;
+pub mod semantic;
+pub mod stream;
+pub mod toc;
+pub mod verify;
+pub mod visit;
 
-pub use parser::Parser;
+pub use collate::Collator;
+pub use dictionary::Dictionary;
+pub use grammar::GrammarLabel;
+pub use index::Index;
+pub use output::{write_file, AtomicFile, Backup};
+pub use parser::{ParseError, ParseOptions, Parser, RawParser, Spans};
+pub use stream::EntryReader;
+pub use toc::TocAllocator;
+pub use verify::verify_roundtrip;
 
 pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String, Error> {
     let mut contents = String::with_capacity(2 << 20);
     File::open(path)?.read_to_string(&mut contents)?;
     Ok(contents)
 }
+
+pub fn open_file<P: AsRef<Path>>(path: P) -> Result<EntryReader<BufReader<File>>, Error> {
+    Ok(EntryReader::new(BufReader::new(File::open(path)?)))
+}
@@ -0,0 +1,160 @@
+//! Crash-safe replacement of output files.
+//!
+//! Output is written to a temporary file next to the target, synced to disk, and renamed over the
+//! target only once complete, so an interrupted run never leaves a truncated file behind.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What to keep of a file about to be replaced.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Backup {
+    #[default]
+    None,
+    /// Copy to `FILE.bak`, replacing any earlier backup.
+    Bak,
+    /// Copy to `FILE.SECONDS.bak`, with the seconds since the Unix epoch.
+    Timestamped,
+}
+
+impl FromStr for Backup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Backup, String> {
+        match s {
+            "none" => Ok(Backup::None),
+            "bak" => Ok(Backup::Bak),
+            "timestamp" => Ok(Backup::Timestamped),
+            _ => Err(format!("unknown backup mode {:?} (expected none, bak or timestamp)", s)),
+        }
+    }
+}
+
+/// A file that replaces `path` only when committed. Dropping it uncommitted removes the
+/// temporary file and leaves `path` untouched.
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    file: Option<File>,
+}
+
+fn with_suffix(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+impl AtomicFile {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<AtomicFile> {
+        let path = path.as_ref().to_path_buf();
+        if path.file_name().is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("{} is not a file path", path.display())));
+        }
+        let mut attempt = 0;
+        loop {
+            let temp = with_suffix(&path, ".", &format!(".{}-{}.tmp", std::process::id(), attempt));
+            match OpenOptions::new().write(true).create_new(true).open(&temp) {
+                Ok(file) => return Ok(AtomicFile { path, temp, file: Some(file) }),
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Sync the written contents to disk, back up the current target as asked, and rename the
+    /// temporary file over the target.
+    pub fn commit(mut self, backup: Backup) -> io::Result<()> {
+        let file = self.file.take().expect("committed twice");
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(&self.path) {
+            file.set_permissions(metadata.permissions())?;
+            let backup_path = match backup {
+                Backup::None => None,
+                Backup::Bak => Some(with_suffix(&self.path, "", ".bak")),
+                Backup::Timestamped => {
+                    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                    Some(with_suffix(&self.path, "", &format!(".{}.bak", secs)))
+                }
+            };
+            if let Some(backup_path) = backup_path {
+                fs::copy(&self.path, backup_path)?;
+            }
+        }
+        drop(file);
+        fs::rename(&self.temp, &self.path)?;
+        sync_parent(&self.path)
+    }
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if dir != Path::new("") => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().expect("write after commit").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().expect("flush after commit").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Atomically replace the file at `path` with `contents`.
+pub fn write_file<P: AsRef<Path>>(path: P, contents: &[u8], backup: Backup) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents)?;
+    file.commit(backup)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_file, AtomicFile, Backup};
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn write_file_test() {
+        let dir = std::env::temp_dir().join(format!("nomster-output-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dict.html");
+        write_file(&path, b"old", Backup::Bak).unwrap();
+        assert!(!dir.join("dict.html.bak").exists());
+        write_file(&path, b"new", Backup::Bak).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read(dir.join("dict.html.bak")).unwrap(), b"old");
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,12 @@
-use nom::hex_digit;
+use ascii::{AsciiFolder, FoldError, Unmapped};
+use grammar::GrammarLabel;
+use nom::{hex_digit, IResult};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::error::Error;
+use std::num::ParseIntError;
+use std::ops::Range;
 use std::fmt::{self, Display, Formatter};
 
 #[derive(Debug)]
@@ -9,76 +17,174 @@ pub struct RawEntry<'a> {
     pub extras: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TaggedEntry<'a> {
     pub tocid: u32,
     pub tags: Vec<EntryTag<'a>>,
-    pub word: &'a str,
+    pub word: Cow<'a, str>,
+}
+
+/// Byte range of a tag in the source, and those of its inner tags in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spans {
+    pub span: Range<usize>,
+    pub children: Vec<Spans>,
+}
+
+impl<'a> TaggedEntry<'a> {
+    /// Spans of the entry and of its tags, given the `source` it was parsed from and its offset
+    /// there. Ranges are derived from the text the entry borrows from `source`, so `None` is
+    /// returned for an entry that does not.
+    pub fn spans(&self, source: &str, start: usize) -> Option<Spans> {
+        let tags_start = text_end(source, &self.word)? + "\">\n".len();
+        let children = seq_spans(source, tags_start, &self.tags, EntryTag::spans)?;
+        let end = seq_end(tags_start, &children) + "</div>\n".len();
+        Some(Spans { span: start..end, children })
+    }
+
+    pub fn into_owned(self) -> TaggedEntry<'static> {
+        TaggedEntry {
+            tocid: self.tocid,
+            tags: self.tags.into_iter().map(EntryTag::into_owned).collect(),
+            word: owned(self.word),
+        }
+    }
 }
 
 impl<'a> Display for TaggedEntry<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "<div id=\"MBP_TOC_{id:X}\" data-ascii=\"{word}\">\n",
-               id = self.tocid, word = self.word)?;
+        writeln!(f, "<div id=\"MBP_TOC_{id:X}\" data-ascii=\"{word}\">",
+                 id = self.tocid, word = self.word)?;
         for t in &self.tags {
             write!(f, "{}", t)?;
         }
-        write!(f, "</div>\n")
+        writeln!(f, "</div>")
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EntryTag<'a> {
-    Blockquote(Vec<SimpleTag<'a>>, Option<&'a str>),
+    Blockquote(Vec<SimpleTag<'a>>, Option<Cow<'a, str>>),
     Para(Vec<ParaTag<'a>>),
-    Pre(&'a str),
+    Pre(Cow<'a, str>),
     LineBreak,
+    Unknown(Cow<'a, str>),
+}
+
+impl<'a> EntryTag<'a> {
+    pub fn spans(&self, source: &str, start: usize) -> Option<Spans> {
+        let (end, children) = match *self {
+            EntryTag::Blockquote(ref tags, ref author_opt) => {
+                let tags_start = start + "<blockquote>\n<p>".len();
+                let children = seq_spans(source, tags_start, tags, SimpleTag::spans)?;
+                let end = match *author_opt {
+                    Some(ref author) => text_end(source, author)? + "</i></blockquote>\n".len(),
+                    None => seq_end(tags_start, &children) + "</p>\n</blockquote>\n".len(),
+                };
+                (end, children)
+            }
+            EntryTag::Para(ref tags) => {
+                let tags_start = start + "<p>".len();
+                let children = seq_spans(source, tags_start, tags, ParaTag::spans)?;
+                (seq_end(tags_start, &children) + "</p>\n".len(), children)
+            }
+            EntryTag::Pre(ref raw_html) => (text_end(source, raw_html)? + "</pre>".len(), Vec::new()),
+            EntryTag::LineBreak => (start + "\n".len(), Vec::new()),
+            EntryTag::Unknown(ref raw_html) => (text_end(source, raw_html)?, Vec::new()),
+        };
+        Some(Spans { span: start..end, children })
+    }
+
+    pub fn into_owned(self) -> EntryTag<'static> {
+        match self {
+            EntryTag::Blockquote(tags, author_opt) =>
+                EntryTag::Blockquote(owned_tags(tags), author_opt.map(owned)),
+            EntryTag::Para(tags) => EntryTag::Para(tags.into_iter().map(ParaTag::into_owned).collect()),
+            EntryTag::Pre(raw_html) => EntryTag::Pre(owned(raw_html)),
+            EntryTag::LineBreak => EntryTag::LineBreak,
+            EntryTag::Unknown(raw_html) => EntryTag::Unknown(owned(raw_html)),
+        }
+    }
 }
 
 impl<'a> Display for EntryTag<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
-            EntryTag::Blockquote(ref tags, author_opt) => {
+            EntryTag::Blockquote(ref tags, ref author_opt) => {
                 write!(f, "<blockquote>\n<p>")?;
                 for t in tags {
                     write!(f, "{}", t)?;
                 }
-                write!(f, "</p>\n")?;
-                if let Some(author) = author_opt {
+                writeln!(f, "</p>")?;
+                if let Some(ref author) = *author_opt {
                     write!(f, "\u{2015}<i>{}</i>", author)?;
                 }
-                write!(f, "</blockquote>\n")?;
+                writeln!(f, "</blockquote>")?;
             }
             EntryTag::Para(ref tags) => {
                 write!(f, "<p>")?;
                 for t in tags {
                     write!(f, "{}", t)?;
                 }
-                write!(f, "</p>\n")?;
+                writeln!(f, "</p>")?;
             }
-            EntryTag::Pre(raw_html) => {
+            EntryTag::Pre(ref raw_html) => {
                 write!(f, "<pre>{}</pre>", raw_html)?;
             }
             EntryTag::LineBreak => {
-                write!(f, "\n")?;
+                writeln!(f)?;
+            }
+            EntryTag::Unknown(ref raw_html) => {
+                write!(f, "{}", raw_html)?;
             }
         }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ParaTag<'a> {
-    Strong(&'a str),
+    Strong(Cow<'a, str>),
     Boxed(Vec<SimpleTag<'a>>),
     BoxedGrammar(Vec<SimpleTag<'a>>),
+    Dquotes(Vec<SimpleTag<'a>>),
     Simple(Vec<SimpleTag<'a>>),
 }
 
+impl<'a> ParaTag<'a> {
+    pub fn spans(&self, source: &str, start: usize) -> Option<Spans> {
+        let (tags, open, close) = match *self {
+            ParaTag::Strong(ref word) => {
+                let end = text_end(source, word)? + "</strong>".len();
+                return Some(Spans { span: start..end, children: Vec::new() });
+            }
+            ParaTag::Boxed(ref tags) | ParaTag::BoxedGrammar(ref tags) => (tags, "[", "]"),
+            ParaTag::Dquotes(ref tags) => (tags, "\u{201C}", "\u{201D}"),
+            ParaTag::Simple(ref tags) => (tags, "", ""),
+        };
+        let tags_start = start + open.len();
+        let children = seq_spans(source, tags_start, tags, SimpleTag::spans)?;
+        Some(Spans { span: start..seq_end(tags_start, &children) + close.len(), children })
+    }
+
+    pub fn into_owned(self) -> ParaTag<'static> {
+        match self {
+            ParaTag::Strong(word) => ParaTag::Strong(owned(word)),
+            ParaTag::Boxed(tags) => ParaTag::Boxed(owned_tags(tags)),
+            ParaTag::BoxedGrammar(tags) => ParaTag::BoxedGrammar(owned_tags(tags)),
+            ParaTag::Dquotes(tags) => ParaTag::Dquotes(owned_tags(tags)),
+            ParaTag::Simple(tags) => ParaTag::Simple(owned_tags(tags)),
+        }
+    }
+}
+
 impl<'a> Display for ParaTag<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
-            ParaTag::Strong(word) => {
+            ParaTag::Strong(ref word) => {
                 write!(f, "<strong>{}</strong>", word)?;
             }
             ParaTag::Boxed(ref tags) | ParaTag::BoxedGrammar(ref tags) => {
@@ -88,6 +194,13 @@ impl<'a> Display for ParaTag<'a> {
                 }
                 write!(f, "]")?;
             }
+            ParaTag::Dquotes(ref tags) => {
+                write!(f, "\u{201C}")?;
+                for t in tags {
+                    write!(f, "{}", t)?;
+                }
+                write!(f, "\u{201D}")?;
+            }
             ParaTag::Simple(ref tags) => {
                 for t in tags {
                     write!(f, "{}", t)?;
@@ -98,47 +211,85 @@ impl<'a> Display for ParaTag<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SimpleTag<'a> {
-    Bold(&'a str),
-    BoxedPlain(&'a str),
+    Bold(Cow<'a, str>),
+    BoxedPlain(Cow<'a, str>),
     BreakTag,
-    Emph(&'a str),
-    Plain(&'a str),
-    SmallB(&'a str),
-    Sub(&'a str),
-    Sup(&'a str),
-    WordRef(u32, &'a str),
+    Emph(Cow<'a, str>),
+    Plain(Cow<'a, str>),
+    SmallB(Cow<'a, str>),
+    Sub(Cow<'a, str>),
+    Sup(Cow<'a, str>),
+    Unknown(Cow<'a, str>),
+    WordRef(u32, Cow<'a, str>),
+}
+
+impl<'a> SimpleTag<'a> {
+    pub fn spans(&self, source: &str, start: usize) -> Option<Spans> {
+        let end = match *self {
+            SimpleTag::Bold(ref text) => text_end(source, text)? + "</b>".len(),
+            SimpleTag::BoxedPlain(ref text) if text.is_empty() => start + "[]".len(),
+            SimpleTag::BoxedPlain(ref text) => text_end(source, text)? + "]".len(),
+            SimpleTag::BreakTag => start + "<br>\n".len(),
+            SimpleTag::Emph(ref text) => text_end(source, text)? + "</i>".len(),
+            SimpleTag::Plain(ref text) | SimpleTag::Unknown(ref text) => text_end(source, text)?,
+            SimpleTag::SmallB(ref text) => text_end(source, text)? + "</b></small>".len(),
+            SimpleTag::Sub(ref text) => text_end(source, text)? + "</sub>".len(),
+            SimpleTag::Sup(ref text) => text_end(source, text)? + "</sup>".len(),
+            SimpleTag::WordRef(_, ref text) => text_end(source, text)? + "</a>".len(),
+        };
+        Some(Spans { span: start..end, children: Vec::new() })
+    }
+
+    pub fn into_owned(self) -> SimpleTag<'static> {
+        match self {
+            SimpleTag::Bold(text) => SimpleTag::Bold(owned(text)),
+            SimpleTag::BoxedPlain(text) => SimpleTag::BoxedPlain(owned(text)),
+            SimpleTag::BreakTag => SimpleTag::BreakTag,
+            SimpleTag::Emph(text) => SimpleTag::Emph(owned(text)),
+            SimpleTag::Plain(text) => SimpleTag::Plain(owned(text)),
+            SimpleTag::SmallB(text) => SimpleTag::SmallB(owned(text)),
+            SimpleTag::Sub(text) => SimpleTag::Sub(owned(text)),
+            SimpleTag::Sup(text) => SimpleTag::Sup(owned(text)),
+            SimpleTag::Unknown(raw_html) => SimpleTag::Unknown(owned(raw_html)),
+            SimpleTag::WordRef(id, text) => SimpleTag::WordRef(id, owned(text)),
+        }
+    }
 }
 
 impl<'a> Display for SimpleTag<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
-            SimpleTag::Bold(text) => {
+            SimpleTag::Bold(ref text) => {
                 write!(f, "<b>{}</b>", text)?;
             }
-            SimpleTag::BoxedPlain(text) => {
+            SimpleTag::BoxedPlain(ref text) => {
                 write!(f, "[{}]", text)?;
             }
             SimpleTag::BreakTag => {
-                write!(f, "<br>\n")?;
+                writeln!(f, "<br>")?;
             }
-            SimpleTag::Emph(text) => {
+            SimpleTag::Emph(ref text) => {
                 write!(f, "<i>{}</i>", text)?;
             }
-            SimpleTag::Plain(text) => {
+            SimpleTag::Plain(ref text) => {
                 write!(f, "{}", text)?;
             }
-            SimpleTag::SmallB(text) => {
+            SimpleTag::SmallB(ref text) => {
                 write!(f, "<small><b>{}</b></small>", text)?;
             }
-            SimpleTag::Sub(text) => {
+            SimpleTag::Sub(ref text) => {
                 write!(f, "<sub>{}</sub>", text)?;
             }
-            SimpleTag::Sup(text) => {
+            SimpleTag::Sup(ref text) => {
                 write!(f, "<sup>{}</sup>", text)?;
             }
-            SimpleTag::WordRef(id, text) => {
+            SimpleTag::Unknown(ref raw_html) => {
+                write!(f, "{}", raw_html)?;
+            }
+            SimpleTag::WordRef(id, ref text) => {
                 write!(f, "<a href=\"#MBP_TOC_{:X}\">{}</a>", id, text)?;
             }
         }
@@ -146,201 +297,554 @@ impl<'a> Display for SimpleTag<'a> {
     }
 }
 
-fn toc_u32(toc: &str) -> u32 {
-    u32::from_str_radix(toc, 16).unwrap()
-}
-
-fn is_gram_marker(text: &str) -> bool {
-    let variants: &[&str] = &[
-        //"1st pers.", "2d pers.", "3d pers.",
-        //"a.",
-        //"adv.",
-        "compar.", "Compar.",
-        "dat.",
-        "imp.",
-        //"indic.",
-        "inf.",
-        //"n.",
-        "nom.",
-        "obj.",
-        "obs.", "Obs.",
-        "p. p.",
-        "p. pr.",
-        //"pass.",
-        "pl.",
-        "poss.", "Poss.",
-        //"prep.",
-        "pres.",
-        "pret.",
-        //"pron.",
-        "sing.", "Sing.",
-        //"subj.",
-        "superl.", "Superl.",
-        //"v.",
-        "v. i.",
-        "v. t.",
-        "vb. n.",
-    ];
-    for v in variants {
-        if text.starts_with(v) {
-            return true;
-        }
-    }
-    false
+/// Offset in `source` of the end of `text`, if it is a slice of `source`. Tags are spanned from
+/// the text they borrow rather than by their rendering, which need not match the source.
+fn text_end(source: &str, text: &str) -> Option<usize> {
+    let offset = (text.as_ptr() as usize).checked_sub(source.as_ptr() as usize)?;
+    Some(offset + text.len()).filter(|&end| end <= source.len())
+}
+
+/// Spans of consecutive tags, the first starting at `start`.
+fn seq_spans<T>(source: &str, mut start: usize, tags: &[T], spans: fn(&T, &str, usize) -> Option<Spans>)
+    -> Option<Vec<Spans>>
+{
+    tags.iter().map(|tag| {
+        let tag_spans = spans(tag, source, start)?;
+        start = tag_spans.span.end;
+        Some(tag_spans)
+    }).collect()
+}
+
+/// End of a sequence of tags starting at `start`.
+fn seq_end(start: usize, children: &[Spans]) -> usize {
+    children.last().map_or(start, |last| last.span.end)
+}
+
+fn owned(text: Cow<str>) -> Cow<'static, str> {
+    Cow::Owned(text.into_owned())
+}
+
+fn owned_tags(tags: Vec<SimpleTag>) -> Vec<SimpleTag<'static>> {
+    tags.into_iter().map(SimpleTag::into_owned).collect()
 }
 
+fn toc_u32(toc: &str) -> Result<u32, ParseIntError> {
+    u32::from_str_radix(toc, 16)
+}
 
-named!(bold<&str, SimpleTag>,
+named!(bold<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("<b>"), is_not!("<>"), tag!("</b>")),
-            |s| SimpleTag::Bold(s)));
-named!(boxed_plain<&str, SimpleTag>,
+            |s| SimpleTag::Bold(s.into())));
+named!(boxed_plain<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("["), opt!(is_not!("<>[]")), tag!("]")),
-            |s_o| SimpleTag::BoxedPlain(s_o.unwrap_or(""))));
-named!(break_tag<&str, SimpleTag>,
+            |s_o| SimpleTag::BoxedPlain(s_o.unwrap_or("").into())));
+named!(break_tag<&str, SimpleTag<'_>>,
        map!(tag!("<br>\n"), |_| SimpleTag::BreakTag));
-named!(emph<&str, SimpleTag>,
+named!(emph<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("<i>"), take_until!("</i>"), tag!("</i>")),
-            |s| SimpleTag::Emph(s)));
-named!(small_b<&str, SimpleTag>,
+            |s| SimpleTag::Emph(s.into())));
+named!(small_b<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("<small><b>"), is_not!("<>"), tag!("</b></small>")),
-            |s| SimpleTag::SmallB(s)));
-named!(sub<&str, SimpleTag>,
+            |s| SimpleTag::SmallB(s.into())));
+named!(sub<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("<sub>"), is_not!("<>"), tag!("</sub>")),
-            |s| SimpleTag::Sub(s)));
-named!(sup<&str, SimpleTag>,
+            |s| SimpleTag::Sub(s.into())));
+named!(sup<&str, SimpleTag<'_>>,
        map!(delimited!(tag!("<sup>"), is_not!("<>"), tag!("</sup>")),
-            |s| SimpleTag::Sup(s)));
-named!(plain<&str, SimpleTag>,
-       map!(is_not!("<>[]"), |s| SimpleTag::Plain(s)));
-named!(plain_nobox<&str, SimpleTag>,
-       map!(is_not!("<>"), |s| SimpleTag::Plain(s)));
-named!(word_ref<&str, SimpleTag>,
-       map!(toc_link, |(id, text)| SimpleTag::WordRef(id, text)));
+            |s| SimpleTag::Sup(s.into())));
+named!(plain<&str, SimpleTag<'_>>,
+       map!(is_not!("<>[]"), |s| SimpleTag::Plain(s.into())));
+named!(plain_nobox<&str, SimpleTag<'_>>,
+       map!(is_not!("<>"), |s| SimpleTag::Plain(s.into())));
+named!(plain_noquote<&str, SimpleTag<'_>>,
+       map!(is_not!("<>[]\u{201C}"), |s| SimpleTag::Plain(s.into())));
+named!(plain_quoted<&str, SimpleTag<'_>>,
+       map!(is_not!("<>[]\u{201D}"), |s| SimpleTag::Plain(s.into())));
+named!(word_ref<&str, SimpleTag<'_>>,
+       map!(toc_link, |(id, text)| SimpleTag::WordRef(id, text.into())));
+
+named!(quote_tag<&str, SimpleTag<'_>>,
+       alt!(plain_nobox | emph | break_tag));
+named!(quote_tags<&str, Vec<SimpleTag<'_>>>, many1!(quote_tag));
 
-named!(quote_tags<&str, Vec<SimpleTag>>,
-       many1!(alt!(plain_nobox | emph | break_tag)));
+named!(boxed_tag<&str, SimpleTag<'_>>,
+       alt!(plain | emph | bold | word_ref | small_b | boxed_plain));
+named!(boxed_tags<&str, Vec<SimpleTag<'_>>>, many1!(boxed_tag));
 
-named!(boxed_tags<&str, Vec<SimpleTag>>,
-       many1!(alt!(plain | emph | bold | word_ref | small_b | boxed_plain)));
+named!(dquote_tag<&str, SimpleTag<'_>>,
+       alt!(plain_quoted | emph | bold | word_ref | small_b | break_tag | boxed_plain | sub | sup));
+named!(dquote_tags<&str, Vec<SimpleTag<'_>>>, many1!(dquote_tag));
 
-named!(simple_tags<&str, Vec<SimpleTag>>,
-       many1!(alt!(plain | emph | bold | word_ref | small_b | break_tag | boxed_plain | sub | sup)));
+named!(simple_tag<&str, SimpleTag<'_>>,
+       alt!(plain_noquote | emph | bold | word_ref | small_b | break_tag | boxed_plain | sub | sup));
+named!(simple_tags<&str, Vec<SimpleTag<'_>>>, many1!(simple_tag));
 
-named!(strong<&str, ParaTag>,
+named!(strong<&str, ParaTag<'_>>,
        map!(delimited!(tag!("<strong>"), is_not!("<>"), tag!("</strong>")),
-            |s| ParaTag::Strong(s)));
-named!(boxed<&str, ParaTag>,
+            |s| ParaTag::Strong(s.into())));
+named!(boxed<&str, ParaTag<'_>>,
        map!(delimited!(tag!("["), boxed_tags, tag!("]")),
             |v| match v[0] {
-                SimpleTag::Emph(text) if is_gram_marker(text) => ParaTag::BoxedGrammar(v),
+                SimpleTag::Emph(ref text) if !GrammarLabel::parse_prefix(text).0.is_empty() =>
+                    ParaTag::BoxedGrammar(v),
+                _ => ParaTag::Boxed(v),
+            }));
+named!(dquotes<&str, ParaTag<'_>>,
+       map!(delimited!(tag!("\u{201C}"), dquote_tags, tag!("\u{201D}")),
+            ParaTag::Dquotes));
+named!(simple<&str, ParaTag<'_>>,
+       map!(simple_tags, ParaTag::Simple));
+named!(stray_ldquo<&str, ParaTag<'_>>,
+       map!(tag!("\u{201C}"), |s| ParaTag::Simple(vec![SimpleTag::Plain(s.into())])));
+named!(para_tag<&str, ParaTag<'_>>,
+       alt!(simple | dquotes | boxed | strong | stray_ldquo));
+
+named!(para<&str, EntryTag<'_>>,
+       map!(delimited!(tag!("<p>"), many1!(para_tag), tag!("</p>\n")),
+            EntryTag::Para));
+named!(pre<&str, EntryTag<'_>>,
+       map!(delimited!(tag!("<pre>"), take_until!("</pre>"), tag!("</pre>")),
+            |s| EntryTag::Pre(s.into())));
+named!(quote_author<&str, &str>,
+       delimited!(tag!("\u{2015}<i>"), is_not!("<>"), tag!("</i>")));
+named!(blockquote<&str, EntryTag<'_>>,
+       map!(delimited!(tag!("<blockquote>\n"),
+                       tuple!(delimited!(tag!("<p>"), quote_tags, tag!("</p>\n")),
+                              opt!(quote_author)),
+                       tag!("</blockquote>\n")),
+            |(v, a_o)| EntryTag::Blockquote(v, a_o.map(Cow::from))));
+named!(line_break<&str, EntryTag<'_>>,
+       map!(tag!("\n"), |_| EntryTag::LineBreak));
+named!(entry_tag<&str, EntryTag<'_>>,
+       alt!(para | pre | blockquote | line_break));
+
+named!(parse_entry<&str, TaggedEntry<'_>>,
+       do_parse!(
+           divo: div_open >>
+           tags: many1!(entry_tag) >>
+           tag!("</div>\n") >>
+           ( TaggedEntry { tocid: divo.0, tags, word: divo.1.into() } )));
+
+// Lenient rules: like the ones above, but markup they do not know is kept as `Unknown`.
+
+named!(unknown_markup<&str, &str>,
+       preceded!(not!(alt!(tag!("</p>") | tag!("</div>"))),
+                 alt!(recognize!(delimited!(tag!("<"), is_not!("<>"), tag!(">"))) |
+                      tag!("<") | tag!(">"))));
+named!(unknown_block<&str, EntryTag<'_>>,
+       map!(preceded!(not!(tag!("</div>\n")), recognize!(terminated!(is_not!("\n"), tag!("\n")))),
+            |s| EntryTag::Unknown(s.into())));
+
+named!(lenient_quote_tag<&str, SimpleTag<'_>>,
+       alt!(quote_tag | map!(unknown_markup, |s| SimpleTag::Unknown(s.into()))));
+named!(lenient_boxed_tag<&str, SimpleTag<'_>>,
+       alt!(boxed_tag | map!(alt!(unknown_markup | tag!("[")), |s| SimpleTag::Unknown(s.into()))));
+named!(lenient_dquote_tag<&str, SimpleTag<'_>>,
+       alt!(dquote_tag | map!(alt!(unknown_markup | tag!("[") | tag!("]")), |s| SimpleTag::Unknown(s.into()))));
+named!(lenient_simple_tag<&str, SimpleTag<'_>>,
+       alt!(simple_tag |
+            map!(preceded!(not!(alt!(lenient_boxed | lenient_dquotes | strong)),
+                           alt!(unknown_markup | tag!("[") | tag!("]"))),
+                 |s| SimpleTag::Unknown(s.into()))));
+
+named!(lenient_boxed<&str, ParaTag<'_>>,
+       map!(delimited!(tag!("["), many1!(lenient_boxed_tag), tag!("]")),
+            |v| match v[0] {
+                SimpleTag::Emph(ref text) if !GrammarLabel::parse_prefix(text).0.is_empty() =>
+                    ParaTag::BoxedGrammar(v),
                 _ => ParaTag::Boxed(v),
             }));
-named!(simple<&str, ParaTag>,
-       map!(simple_tags, |v| ParaTag::Simple(v)));
+named!(lenient_dquotes<&str, ParaTag<'_>>,
+       map!(delimited!(tag!("\u{201C}"), many1!(lenient_dquote_tag), tag!("\u{201D}")),
+            ParaTag::Dquotes));
+named!(lenient_simple<&str, ParaTag<'_>>,
+       map!(many1!(lenient_simple_tag), ParaTag::Simple));
+named!(lenient_para_tag<&str, ParaTag<'_>>,
+       alt!(lenient_simple | lenient_dquotes | lenient_boxed | strong | stray_ldquo));
+
+named!(lenient_para<&str, EntryTag<'_>>,
+       map!(delimited!(tag!("<p>"), many1!(lenient_para_tag), tag!("</p>\n")),
+            EntryTag::Para));
+named!(lenient_blockquote<&str, EntryTag<'_>>,
+       map!(delimited!(tag!("<blockquote>\n"),
+                       tuple!(delimited!(tag!("<p>"), many1!(lenient_quote_tag), tag!("</p>\n")),
+                              opt!(quote_author)),
+                       tag!("</blockquote>\n")),
+            |(v, a_o)| EntryTag::Blockquote(v, a_o.map(Cow::from))));
+named!(lenient_entry_tag<&str, EntryTag<'_>>,
+       alt!(lenient_para | pre | lenient_blockquote | line_break | unknown_block));
 
-named!(parse_entry<&str, TaggedEntry>,
+named!(parse_entry_lenient<&str, TaggedEntry<'_>>,
        do_parse!(
            divo: div_open >>
-           tags: many1!(
-               alt!(map!(delimited!(tag!("<p>"),
-                                    many1!(alt!(simple | boxed | strong)),
-                                    tag!("</p>\n")),
-                         |v| EntryTag::Para(v)) |
-                    map!(delimited!(tag!("<pre>"),
-                                    take_until!("</pre>"),
-                                    tag!("</pre>")),
-                         |s| EntryTag::Pre(s)) |
-                    map!(delimited!(tag!("<blockquote>\n"),
-                                    tuple!(
-                                        delimited!(tag!("<p>"), quote_tags, tag!("</p>\n")),
-                                        opt!(delimited!(tag!("\u{2015}<i>"),
-                                                        is_not!("<>"),
-                                                        tag!("</i>")))),
-                                    tag!("</blockquote>\n")),
-                         |(v, a_o)| EntryTag::Blockquote(v, a_o)) |
-                    map!(tag!("\n"), |_| EntryTag::LineBreak))) >>
+           tags: many1!(lenient_entry_tag) >>
            tag!("</div>\n") >>
-           ( TaggedEntry { tocid: divo.0, tags: tags, word: divo.1 } )));
+           ( TaggedEntry { tocid: divo.0, tags, word: divo.1.into() } )));
+
+fn skip_many<'a, O, F>(mut input: &'a str, item: F) -> &'a str
+    where F: Fn(&'a str) -> IResult<&'a str, O>
+{
+    while let Ok((rest, _)) = item(input) {
+        if rest.len() == input.len() {
+            break;
+        }
+        input = rest;
+    }
+    input
+}
+
+/// Find the innermost rule that rejects `entry`, and the input it was given.
+fn diagnose(entry: &str) -> (&'static str, &str) {
+    let rest = match div_open(entry) {
+        Ok((rest, _)) => skip_many(rest, entry_tag),
+        Err(_) => return ("div_open", entry),
+    };
+    if let Some(rest) = rest.strip_prefix("<p>") {
+        let rest = skip_many(rest, para_tag);
+        if let Some(inner) = rest.strip_prefix('[') {
+            ("boxed_tags", skip_many(inner, boxed_tag))
+        } else if rest.starts_with("<strong>") {
+            ("strong", rest)
+        } else if rest.starts_with("</p>") {
+            ("para", rest)
+        } else {
+            ("simple_tags", rest)
+        }
+    } else if let Some(rest) = rest.strip_prefix("<blockquote>\n<p>") {
+        let rest = skip_many(rest, quote_tag);
+        if rest.starts_with("</p>\n") {
+            ("blockquote", rest)
+        } else {
+            ("quote_tags", rest)
+        }
+    } else if rest.starts_with("<blockquote>") {
+        ("blockquote", rest)
+    } else if rest.starts_with("<pre>") {
+        ("pre", rest)
+    } else if rest.is_empty() {
+        ("div_close", rest)
+    } else {
+        ("entry_tag", rest)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError<'a> {
+    /// Id of the entry, if its opening `<div>` was readable.
+    pub tocid: Option<u32>,
+    /// Byte offset of the failure in the source.
+    pub offset: usize,
+    /// 1-based line number of the failure in the source.
+    pub line: usize,
+    /// 1-based column (in chars) of the failure in the source.
+    pub column: usize,
+    /// Innermost grammar rule that rejected the input, or `spans` if its source spans could not
+    /// be found.
+    pub rule: &'static str,
+    /// Rejected input, up to the end of its line (at most 40 chars).
+    pub snippet: &'a str,
+    /// Text of the whole entry.
+    pub entry: &'a str,
+}
+
+/// A line of the source: its 1-based number and the byte offset it starts at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LineStart {
+    pub line: usize,
+    pub offset: usize,
+}
+
+impl Default for LineStart {
+    fn default() -> LineStart {
+        LineStart { line: 1, offset: 0 }
+    }
+}
+
+impl LineStart {
+    /// Move past `text`, which is at `offset` in the source and on or after this line.
+    pub fn advance(&mut self, text: &str, offset: usize) {
+        if let Some(last) = text.rfind('\n') {
+            self.line += text.matches('\n').count();
+            self.offset = offset + last + 1;
+        }
+    }
+}
+
+impl<'a> ParseError<'a> {
+    /// An error in `entry`, which is part of `source` and starts on or after the line `from`.
+    fn new(source: &'a str, entry: &'a str, from: LineStart) -> ParseError<'a> {
+        let (rule, rest) = diagnose(entry);
+        ParseError::at(source, entry, from, rule, rest)
+    }
+
+    /// An error in `entry` where `rule` rejected `rest`, a suffix of `entry`.
+    fn at(source: &'a str, entry: &'a str, from: LineStart, rule: &'static str, rest: &'a str)
+        -> ParseError<'a>
+    {
+        let tocid = div_open(entry).ok().map(|(_, (tocid, _))| tocid);
+        let offset = rest.as_ptr() as usize - source.as_ptr() as usize;
+        let mut at = from;
+        at.advance(&source[from.offset..offset], from.offset);
+        let snippet_len = rest.char_indices()
+                              .take_while(|&(_, c)| c != '\n')
+                              .take(40)
+                              .last()
+                              .map_or(0, |(i, c)| i + c.len_utf8());
+        ParseError {
+            tocid,
+            offset,
+            line: at.line,
+            column: source[at.offset..offset].chars().count() + 1,
+            rule,
+            snippet: &rest[..snippet_len],
+            entry,
+        }
+    }
+}
+
+impl<'a> Display for ParseError<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        if let Some(tocid) = self.tocid {
+            write!(f, "MBP_TOC_{:X}: ", tocid)?;
+        }
+        write!(f, "{} failed at {:?}", self.rule, self.snippet)
+    }
+}
+
+impl<'a> Error for ParseError<'a> {}
 
 named!(entry_start<&str, &str>, take_until!("<div id=\"MBP_"));
 
 named!(pub toc_link<&str, (u32, &str)>,
        do_parse!(
            tag!("<a href=\"#MBP_TOC_") >>
-           tocid: map!(hex_digit, toc_u32) >>
+           tocid: map_res!(hex_digit, toc_u32) >>
            tag!("\">") >>
            text: is_not!("<>") >>
            tag!("</a>") >>
            ( (tocid, text) )
       ));
 
+named!(raw_entry_start<&str, &str>, take_until!("<p id=\"MBP_TOC_"));
+
+named!(raw_entry<&str, (u32, &str, &str)>,
+       do_parse!(
+           tag!("<p id=\"MBP_TOC_") >>
+           tocid: map_res!(hex_digit, toc_u32) >>
+           tag!("\"><big><b>") >>
+           word: take_until_and_consume!("</b></big>") >>
+           body: take_until_and_consume!("</p>") >>
+           ( tocid, word, body )
+      ));
+
+named!(raw_extra<&str, &str>,
+       alt!(tag!("\n") |
+            recognize!(delimited!(tag!("<p>"), take_until!("</p>"), tag!("</p>"))) |
+            recognize!(delimited!(tag!("<pre>"), take_until!("</pre>"), tag!("</pre>"))) |
+            recognize!(delimited!(tag!("<blockquote>"),
+                                  take_until!("</blockquote>"),
+                                  tag!("</blockquote>")))));
+
+fn raw_extras(input: &str) -> (&str, &str) {
+    let rest = skip_many(input, raw_extra);
+    input.split_at(input.len() - rest.len())
+}
+
 named!(div_open<&str, (u32, &str)>,
        do_parse!(
            tag!("<div id=\"MBP_TOC_") >>
-           tocid: map!(hex_digit, toc_u32) >>
+           tocid: map_res!(hex_digit, toc_u32) >>
            tag!("\" data-ascii=\"") >>
            word: take_until_and_consume!("\">\n") >>
            ( tocid, word )
       ));
 
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// Keep markup unknown to the grammar as `Unknown` tags instead of failing the entry.
+    pub lenient: bool,
+}
+
 pub struct Parser<'a> {
+    source: &'a str,
     contents: &'a str,
+    /// The line `contents` starts on.
+    line: LineStart,
+    /// The line the entry last returned starts on.
+    entry_start: LineStart,
+    options: ParseOptions,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(contents: &'a str) -> Parser<'a> {
-        Parser { contents }
+        Parser::with_options(contents, ParseOptions::default())
+    }
+
+    pub fn with_options(contents: &'a str, options: ParseOptions) -> Parser<'a> {
+        let line = LineStart::default();
+        Parser { source: contents, contents, line, entry_start: line, options }
     }
 
     pub fn remaining(&self) -> &'a str {
         self.contents
     }
+
+    /// The 1-based line the entry last returned by `next` starts on.
+    pub fn entry_line(&self) -> usize {
+        self.entry_start.line
+    }
+
+    /// Also yield the spans of each entry, as byte offsets into the parsed contents.
+    pub fn spanned(self) -> SpannedParser<'a> {
+        SpannedParser { parser: self }
+    }
 }
 
 impl<'a> Iterator for Parser<'a> {
-    type Item = (&'a str, Result<TaggedEntry<'a>, &'a str>);
+    type Item = (&'a str, Result<TaggedEntry<'a>, ParseError<'a>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (skipped, entry_str, next) = split_next(self.contents)?;
+        let offset = self.source.len() - self.contents.len();
+        self.line.advance(skipped, offset);
+        self.entry_start = self.line;
+        let entry = parse_entry_str(self.source, entry_str, self.line, self.options);
+        self.line.advance(entry_str, offset + skipped.len());
+        self.contents = next;
+        Some((skipped, entry))
+    }
+}
+
+pub struct SpannedParser<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> SpannedParser<'a> {
+    pub fn remaining(&self) -> &'a str {
+        self.parser.remaining()
+    }
+}
+
+impl<'a> Iterator for SpannedParser<'a> {
+    type Item = (&'a str, Result<(TaggedEntry<'a>, Spans), ParseError<'a>>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Ok((remaining, skipped)) = entry_start(self.contents) {
-            let end_idx = remaining.find("</div>\n").expect("entry did not end properly") + 7;
-            let entry_str = &remaining[..end_idx];
-            self.contents = &remaining[end_idx..];
-            if let Ok((empty, entry_tags)) = parse_entry(entry_str) {
-                assert!(empty.is_empty());
-                Some((skipped, Ok(entry_tags)))
-            } else {
-                Some((skipped, Err(entry_str)))
+        let (skipped, entry) = self.parser.next()?;
+        let source = self.parser.source;
+        let start = text_end(source, skipped).unwrap_or(0);
+        Some((skipped, entry.and_then(|entry| match entry.spans(source, start) {
+            Some(spans) => Ok((entry, spans)),
+            None => {
+                let entry_str = &source[start..source.len() - self.parser.remaining().len()];
+                Err(ParseError::at(source, entry_str, self.parser.entry_start, "spans", entry_str))
             }
-        } else {
-            None
+        })))
+    }
+}
+
+/// Parse the next `<div id="MBP_TOC_…">` entry into its tags.
+///
+/// Returns the text skipped before the entry, the entry (or why it failed to parse) and the
+/// contents following it. Error positions are relative to `contents`.
+pub fn next_entry2(contents: &str) -> Option<(&str, Result<TaggedEntry<'_>, ParseError<'_>>, &str)> {
+    let (skipped, entry_str, next) = split_next(contents)?;
+    Some((skipped, parse_entry_str(contents, entry_str, LineStart::default(), ParseOptions::default()), next))
+}
+
+/// Split off the next `<div>` entry without parsing it, returning the text skipped before it, the
+/// entry up to and including its `</div>\n`, and the contents following it. An entry missing its
+/// `</div>\n` ends where the next entry starts.
+pub(crate) fn split_next(contents: &str) -> Option<(&str, &str, &str)> {
+    let (remaining, skipped) = entry_start(contents).ok()?;
+    let next_start = remaining[1..].find("<div id=\"MBP_").map_or(remaining.len(), |i| i + 1);
+    let end_idx = remaining[..next_start].find("</div>\n").map_or(next_start, |i| i + 7);
+    let (entry_str, next) = remaining.split_at(end_idx);
+    Some((skipped, entry_str, next))
+}
+
+/// The tocid and `data-ascii` word of an entry, read from its opening `<div>` alone.
+pub(crate) fn entry_header(entry_str: &str) -> Option<(u32, &str)> {
+    div_open(entry_str).ok().map(|(_, header)| header)
+}
+
+/// Parse a single entry, from its `<div>` up to and including its `</div>\n`. `from` is a line of
+/// `source` at or before the entry, from which error positions are counted.
+pub(crate) fn parse_entry_str<'a>(source: &'a str, entry_str: &'a str, from: LineStart, options: ParseOptions)
+    -> Result<TaggedEntry<'a>, ParseError<'a>>
+{
+    let parsed = if options.lenient {
+        parse_entry_lenient(entry_str)
+    } else {
+        parse_entry(entry_str)
+    };
+    match parsed {
+        Ok(("", entry_tags)) => Ok(entry_tags),
+        _ => Err(ParseError::new(source, entry_str, from)),
+    }
+}
+
+/// Parse the next entry in its original `<p id="MBP_TOC_…">` form.
+///
+/// Returns the text skipped before the entry, the entry (or the text that failed to parse, up to
+/// the start of the next entry) and the contents following it.
+pub fn next_entry(contents: &str) -> Option<(&str, Result<RawEntry<'_>, &str>, &str)> {
+    let (remaining, skipped) = raw_entry_start(contents).ok()?;
+    match raw_entry(remaining) {
+        Ok((rest, (tocid, word, body))) => {
+            let (extras, next) = raw_extras(rest);
+            Some((skipped, Ok(RawEntry { word, tocid, body, extras }), next))
+        }
+        Err(_) => {
+            let end_idx = remaining[1..].find("<p id=\"MBP_TOC_").map_or(remaining.len(), |i| i + 1);
+            Some((skipped, Err(&remaining[..end_idx]), &remaining[end_idx..]))
         }
     }
 }
 
+pub struct RawParser<'a> {
+    contents: &'a str,
+}
+
+impl<'a> RawParser<'a> {
+    pub fn new(contents: &'a str) -> RawParser<'a> {
+        RawParser { contents }
+    }
+
+    pub fn remaining(&self) -> &'a str {
+        self.contents
+    }
+}
+
+impl<'a> Iterator for RawParser<'a> {
+    type Item = (&'a str, Result<RawEntry<'a>, &'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_entry(self.contents).map(|(skipped, entry, next)| {
+            self.contents = next;
+            (skipped, entry)
+        })
+    }
+}
+
 pub fn strip_stress(word: &str) -> String {
-    word.replace(|c| c == '´' || c == '•', "")
+    word.replace(['´', '•'], "")
 }
 
 /// lexicographic translation to ascii
-pub fn word_to_ascii(word: &str) -> String {
-    let mut word = strip_stress(word);
-    word = word.chars().map(|c| match c as u32 {
-                                    199 => 'C', 224 => 'a', 225 => 'a', 226 => 'a',
-                                    227 => 'a', 228 => 'a', 231 => 'a', 232 => 'e',
-                                    234 => 'e', 235 => 'e', 238 => 'i', 239 => 'i',
-                                    241 => 'n', 243 => 'o', 244 => 'o', 246 => 'o',
-                                    249 => 'u', 251 => 'u', 252 => 'u',
-                                    7497 => 'e', 7511 => 't', 8217 => '\'',
-                                    _ => c,
-                                }).collect();
-    word = word.replace('\u{0152}', "OE").replace('\u{0153}', "oe");
-    assert!(word.is_ascii());
-    word
+pub fn word_to_ascii(word: &str) -> Result<String, FoldError> {
+    AsciiFolder::default().fold(word)
+}
+
+/// `word_to_ascii`, keeping the characters it has no translation for.
+pub fn fold_to_ascii(word: &str) -> String {
+    AsciiFolder::new(Unmapped::Keep).fold(word).unwrap_or_default()
 }
 
 #[cfg(test)]
 mod test {
-    use super::{strip_stress, word_to_ascii};
+    use super::{next_entry, next_entry2, strip_stress, word_to_ascii, EntryTag, ParaTag, ParseOptions, Parser,
+                RawParser, SimpleTag};
 
     #[test]
     fn strip_stress_test() {
@@ -350,11 +854,165 @@ mod test {
         assert_eq!(strip_stress("Zee´man ef•fect´"), "Zeeman effect");
     }
 
+    #[test]
+    fn next_entry_test() {
+        let contents = "<body>\n<p id=\"MBP_TOC_1A\"><big><b>A•base´</b></big> v. t. To lower.</p>\n\
+                        <blockquote>Cast down.</blockquote>\n\
+                        <p id=\"MBP_TOC_1B\"><big><b>A•bash´</b></big> v. t.</p>\n</body>\n";
+        let (skipped, entry, next) = next_entry(contents).unwrap();
+        let entry = entry.unwrap();
+        assert_eq!(skipped, "<body>\n");
+        assert_eq!((entry.tocid, entry.word), (0x1A, "A•base´"));
+        assert_eq!(entry.body, " v. t. To lower.");
+        assert_eq!(entry.extras, "\n<blockquote>Cast down.</blockquote>\n");
+        assert!(next.starts_with("<p id=\"MBP_TOC_1B\">"));
+
+        let mut parser = RawParser::new(next);
+        let (_, entry) = parser.next().unwrap();
+        assert_eq!(entry.unwrap().extras, "\n");
+        assert!(parser.next().is_none());
+        assert_eq!(parser.remaining(), "</body>\n");
+
+        let (_, entry, next) = next_entry("<p id=\"MBP_TOC_1C\"><b>x</b>\n<p id=\"MBP_TOC_1D\">").unwrap();
+        assert_eq!(entry.unwrap_err(), "<p id=\"MBP_TOC_1C\"><b>x</b>\n");
+        assert_eq!(next, "<p id=\"MBP_TOC_1D\">");
+    }
+
+    #[test]
+    fn dquotes_test() {
+        let contents = "<div id=\"MBP_TOC_2F\" data-ascii=\"Abide\">\n\
+                        <p><strong>A•bide´</strong> To wait. \u{201C}Abide with me.<br>\n<i>Lyte.</i>\u{201D} \
+                        An \u{201C}odd <i>one</i></p>\n</div>\n";
+        let (skipped, entry, next) = next_entry2(contents).unwrap();
+        let entry = entry.unwrap();
+        assert_eq!((skipped, next), ("", ""));
+        assert_eq!(entry.to_string(), contents);
+        match entry.tags[0] {
+            super::EntryTag::Para(ref ptags) => {
+                assert_eq!(ptags.len(), 6);
+                match ptags[2] {
+                    ParaTag::Dquotes(ref qtags) => assert_eq!(qtags.len(), 3),
+                    ref ptag => panic!("unexpected {:?}", ptag),
+                }
+            }
+            ref etag => panic!("unexpected {:?}", etag),
+        }
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let contents = "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p><strong>A</strong></p>\n</div>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p><strong>B</strong> \
+                        [<i>pl.</i> <u>Bs</u>]</p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"C\"><p></p>\n</div>\n";
+        let mut parser = Parser::new(contents);
+        assert!(parser.next().unwrap().1.is_ok());
+        let err = parser.next().unwrap().1.unwrap_err();
+        assert_eq!((err.tocid, err.rule, err.snippet), (Some(0xB), "boxed_tags", "<u>Bs</u>]</p>"));
+        assert_eq!((err.line, err.column), (5, 35));
+        assert_eq!(&contents[err.offset..err.offset + 3], "<u>");
+        assert!(err.entry.starts_with("<div id=\"MBP_TOC_B\"") && err.entry.ends_with("</div>\n"));
+        assert_eq!(err.to_string(), "5:35: MBP_TOC_B: boxed_tags failed at \"<u>Bs</u>]</p>\"");
+        let err = parser.next().unwrap().1.unwrap_err();
+        assert_eq!((err.tocid, err.rule, err.line, err.column), (None, "div_open", 7, 1));
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn malformed_test() {
+        let contents = "<div id=\"MBP_TOC_100000000\" data-ascii=\"A\">\n<p>A</p>\n</div>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p><a href=\"#MBP_TOC_FFFFFFFFF\">A</a></p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"C\">\n<p>C</p>\n";
+        let rules: Vec<_> = Parser::new(contents).map(|(_, e)| e.unwrap_err().rule).collect();
+        assert_eq!(rules, ["div_open", "simple_tags", "div_close"]);
+
+        let unclosed = "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p>A</p>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p>B</p>\n</div>\n";
+        let mut parser = Parser::new(unclosed);
+        let err = parser.next().unwrap().1.unwrap_err();
+        assert_eq!((err.tocid, err.rule), (Some(0xA), "div_close"));
+        assert!(err.entry.ends_with("<p>A</p>\n"));
+        assert_eq!(parser.next().unwrap().1.unwrap().tocid, 0xB);
+        assert!(parser.next().is_none());
+
+        let (_, entry, next) = next_entry("<p id=\"MBP_TOC_FFFFFFFFF\"><big><b>A</b></big></p>\n").unwrap();
+        assert!(entry.is_err() && next.is_empty());
+    }
+
+    #[test]
+    fn lenient_test() {
+        let contents = "<div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p><strong>B</strong> \
+                        [<i>pl.</i> <u>Bs</u>] a <span class=\"x\">&amp;</span> [ b</p>\n\
+                        <hr>\n</div>\n";
+        assert!(Parser::new(contents).next().unwrap().1.is_err());
+        let options = ParseOptions { lenient: true };
+        let entry = Parser::with_options(contents, options).next().unwrap().1.unwrap();
+        assert_eq!(entry.to_string(), contents);
+        assert_eq!(entry.tags[1], EntryTag::Unknown("<hr>\n".into()));
+        match entry.tags[0] {
+            EntryTag::Para(ref ptags) => match ptags[2] {
+                ParaTag::BoxedGrammar(ref stags) => assert_eq!(stags[2], SimpleTag::Unknown("<u>".into())),
+                ref ptag => panic!("unexpected {:?}", ptag),
+            },
+            ref etag => panic!("unexpected {:?}", etag),
+        }
+    }
+
+    #[test]
+    fn into_owned_test() {
+        let contents = String::from("<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n\
+                                     <p><strong>A</strong> <i>n.</i></p>\n</div>\n");
+        let mut entry = Parser::new(&contents).next().unwrap().1.unwrap().into_owned();
+        drop(contents);
+        if let EntryTag::Para(ref mut ptags) = entry.tags[0] {
+            ptags.push(ParaTag::Simple(vec![SimpleTag::Plain(format!(" See {}.", 'B').into())]));
+        }
+        assert_eq!(entry.to_string(), "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n\
+                                       <p><strong>A</strong> <i>n.</i> See B.</p>\n</div>\n");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn serde_test() {
+        extern crate serde_json;
+        use super::TaggedEntry;
+
+        let contents = "<div id=\"MBP_TOC_1A\" data-ascii=\"Abide\">\n<p><strong>A•bide´</strong> \
+                        [<i>imp.</i> <a href=\"#MBP_TOC_1B\">Abode</a>]</p>\n<blockquote>\n\
+                        <p>\u{201C}Abide\u{201D} with me.</p>\n\u{2015}<i>Lyte.</i></blockquote>\n</div>\n";
+        let entry = Parser::new(contents).next().unwrap().1.unwrap();
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.starts_with("{\"tocid\":26,\"tags\":[{\"Para\":[{\"Strong\":\"A•bide´\"}"));
+        let decoded: TaggedEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, entry);
+        assert_eq!(decoded.to_string(), contents);
+    }
+
+    #[test]
+    fn spanned_test() {
+        let contents = "<html>\n<div id=\"MBP_TOC_0A\" data-ascii=\"A\">\n<p><strong>A</strong> \
+                        [<i>pl.</i> <b>As</b>] See <a href=\"#MBP_TOC_00B\">B</a>.</p>\n<blockquote>\n\
+                        <p>a<br>\nb</p>\n</blockquote>\n</div>\n";
+        let (_, entry) = Parser::new(contents).spanned().next().unwrap();
+        let (entry, spans) = entry.unwrap();
+        assert_eq!(spans.span, "<html>\n".len()..contents.len());
+        let boxed = &spans.children[0].children[2];
+        assert_eq!(&contents[boxed.span.clone()], "[<i>pl.</i> <b>As</b>]");
+        assert_eq!(&contents[boxed.children[2].span.clone()], "<b>As</b>");
+        let link = &spans.children[0].children[3].children[1];
+        assert_eq!(&contents[link.span.clone()], "<a href=\"#MBP_TOC_00B\">B</a>");
+        let quote = &spans.children[1];
+        assert_eq!(&contents[quote.children[1].span.clone()], "<br>\n");
+        assert_eq!(quote.span.end + "</div>\n".len(), contents.len());
+        assert!(entry.into_owned().spans(contents, spans.span.start).is_none());
+    }
+
     #[test]
     fn word_to_ascii_test() {
-        assert_eq!(word_to_ascii("A•mœ´ba"), "Amoeba");
-        assert_eq!(word_to_ascii("Law´giv•er"), "Lawgiver");
-        assert_eq!(word_to_ascii("Zöll´ner’s Lines"), "Zollner's Lines");
-        assert_eq!(word_to_ascii("Zee´man-ef•fect´"), "Zeeman-effect");
+        assert_eq!(word_to_ascii("A•mœ´ba").unwrap(), "Amoeba");
+        assert_eq!(word_to_ascii("Law´giv•er").unwrap(), "Lawgiver");
+        assert_eq!(word_to_ascii("Zöll´ner’s Lines").unwrap(), "Zollner's Lines");
+        assert_eq!(word_to_ascii("Zee´man-ef•fect´").unwrap(), "Zeeman-effect");
+        assert_eq!(word_to_ascii("\u{2603}").unwrap_err().character, '\u{2603}');
     }
 }
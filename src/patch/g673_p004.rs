@@ -0,0 +1,75 @@
+use patch::Patch;
+
+named!(till_hwhw<&str, &str>, take_until!("<hw><hw>"));
+named!(hw_proper<&str, (&str, &str, &str)>, tuple!(tag!("<hw>"), take_until!("</hw>"), tag!("</hw>")));
+
+/// Unwraps headwords doubled as `<hw><hw>...}` in the g673 source.
+pub struct NestedHeadwords;
+
+impl Patch for NestedHeadwords {
+    fn id(&self) -> &'static str {
+        "g673-004"
+    }
+
+    fn description(&self) -> &'static str {
+        "unwrap doubled <hw><hw> headwords in the g673 source"
+    }
+
+    fn apply(&mut self, mut contents: &str, log: &mut Vec<String>) -> String {
+        let mut patched = String::with_capacity(contents.len());
+        loop {
+            match till_hwhw(contents) {
+                Ok((remaining, consumed)) => {
+                    patched.push_str(consumed);
+                    let (line, next) = remaining.split_at(remaining.find('\n').unwrap_or(remaining.len()));
+                    contents = next;
+                    match unwrap_line(&line[4..]) {
+                        Some(unwrapped) => patched.push_str(&unwrapped),
+                        None => {
+                            log.push(format!("no closing '}}' in {:?}", line));
+                            patched.push_str(line);
+                        }
+                    }
+                }
+                Err(_) => {
+                    patched.push_str(contents);
+                    break;
+                }
+            }
+        }
+        patched
+    }
+}
+
+/// Unwrap the headwords of a line that had a doubled `<hw><hw>`, given with the outer `<hw>`
+/// removed. Returns `None` if the line lacks the `}` closing the outer headword.
+fn unwrap_line(mut line: &str) -> Option<String> {
+    let mut patched = String::with_capacity(line.len());
+    loop {
+        match hw_proper(line) {
+            Ok((remaining, (open, inside, close))) => {
+                patched.push_str(open);
+                patched.push_str(inside);
+                patched.push_str(close);
+                line = remaining;
+            }
+            Err(_) => { // unclosed <hw>
+                patched.push_str(line.trim_start_matches("<hw>"));
+                return Some(patched);
+            }
+        }
+        match line.find("<hw>") {
+            Some(idx) => {
+                patched.push_str(&line[..idx]);
+                line = &line[idx..];
+            }
+            None => {
+                // no more <hw>; there must be a '}' then.
+                let idx = line.find('}')?;
+                patched.push_str(&line[..idx]);
+                patched.push_str(&line[idx + 1..]);
+                return Some(patched);
+            }
+        }
+    }
+}
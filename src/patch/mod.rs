@@ -0,0 +1,271 @@
+//! Named, replayable fixes to the dictionary source.
+//!
+//! Each patch rewrites a whole document, most of them one entry at a time through
+//! `patch_raw_entries` or `patch_entries`. Patches are looked up by id in the registry so that a
+//! chain of them can be replayed from the original source.
+
+use diff;
+use parser::{self, LineStart, Parser, RawEntry, TaggedEntry};
+use std::collections::HashMap;
+use std::fmt::Write;
+use toc::TocAllocator;
+
+mod g673_p004;
+mod p004;
+mod p007;
+mod p018;
+mod p019;
+
+pub trait Patch {
+    /// Short id used to select the patch, like `"018"`.
+    fn id(&self) -> &'static str;
+
+    /// One-line summary of what the patch fixes.
+    fn description(&self) -> &'static str;
+
+    /// Patch `contents`, returning the new document. Anything worth reporting, including entries
+    /// that could not be patched, is pushed to `log`.
+    fn apply(&mut self, contents: &str, log: &mut Vec<String>) -> String;
+
+    /// Where a patch adding entries takes their tocids from, so that a caller can hand it the
+    /// allocator shared by the corpus and save what it allocated.
+    fn toc_allocator(&mut self) -> Option<&mut TocAllocator> {
+        None
+    }
+}
+
+/// All known patches, in the order they were applied to the corpus.
+pub fn registry() -> Vec<Box<dyn Patch>> {
+    vec![
+        Box::new(p004::CurlyAlternates::default()),
+        Box::new(p007::DivEntries),
+        Box::new(p018::UnlinkGrammar),
+        Box::new(p019::QuotesToBlockquotes),
+        Box::new(g673_p004::NestedHeadwords),
+    ]
+}
+
+pub fn find(id: &str) -> Option<Box<dyn Patch>> {
+    registry().into_iter().find(|patch| patch.id() == id)
+}
+
+/// Apply `f` to every entry in the `<p id="MBP_TOC_x">` format, copying everything between
+/// entries through. `f` writes the patched entry out, or returns `false` without writing anything
+/// to keep the entry unchanged. Entries that fail to parse are logged and copied through unchanged.
+pub fn patch_raw_entries<F>(mut contents: &str, log: &mut Vec<String>, mut f: F) -> String
+    where F: FnMut(&mut String, RawEntry, &mut Vec<String>) -> bool
+{
+    let mut patched = String::with_capacity(contents.len());
+    while let Some((skipped, entry, next)) = parser::next_entry(contents) {
+        let text = &contents[skipped.len()..contents.len() - next.len()];
+        contents = next;
+        patched.push_str(skipped);
+        match entry {
+            Ok(entry) => if !f(&mut patched, entry, log) {
+                patched.push_str(text);
+            },
+            Err(text) => {
+                log.push(format!("unparsed entry: {:?}", text.lines().next().unwrap_or("")));
+                patched.push_str(text);
+            }
+        }
+    }
+    patched.push_str(contents);
+    patched
+}
+
+/// Replace every entry in the `<div id="MBP_TOC_x">` format by the result of `f`, copying
+/// everything between entries through. Entries that fail to parse are logged and copied through
+/// unchanged.
+pub fn patch_entries<'a, F>(contents: &'a str, log: &mut Vec<String>, mut f: F) -> String
+    where F: FnMut(TaggedEntry<'a>, &mut Vec<String>) -> TaggedEntry<'a>
+{
+    let mut patched = String::with_capacity(contents.len());
+    let mut entries = Parser::new(contents);
+    for (skipped, entry) in entries.by_ref() {
+        patched.push_str(skipped);
+        match entry {
+            Ok(entry) => {
+                let entry = f(entry, log);
+                write!(patched, "{}", entry).unwrap();
+            }
+            Err(err) => {
+                log.push(err.to_string());
+                patched.push_str(err.entry);
+            }
+        }
+    }
+    patched.push_str(entries.remaining());
+    patched
+}
+
+/// An entry whose text differs between two versions of a document.
+#[derive(Debug)]
+pub struct EntryDiff<'a> {
+    /// `None` for the text before the first entry.
+    pub tocid: Option<u32>,
+    pub word: &'a str,
+    /// 1-based line of the entry in the old document, or in the new one if it was added.
+    pub line: usize,
+    /// Empty if the entry was added.
+    pub old: &'a str,
+    /// Empty if the entry was removed.
+    pub new: &'a str,
+}
+
+impl<'a> EntryDiff<'a> {
+    pub fn diff(&self) -> String {
+        diff::unified_diff(self.old, self.new, 3)
+    }
+
+    /// Number of lines inserted and deleted.
+    pub fn line_stat(&self) -> (usize, usize) {
+        diff::line_stat(self.old, self.new)
+    }
+}
+
+struct Chunk<'a> {
+    tocid: Option<u32>,
+    word: &'a str,
+    line: usize,
+    text: &'a str,
+}
+
+/// Split `contents` into chunks starting at the line of each `id="MBP_TOC_x"` anchor, in either
+/// the `<p id>` or the `<div id>` entry format.
+fn split_entries(contents: &str) -> Vec<Chunk<'_>> {
+    const ANCHOR: &str = "id=\"MBP_TOC_";
+    let mut chunks = Vec::new();
+    let (mut start, mut line, mut tocid) = (0, LineStart::default(), None);
+    let mut from = 0;
+    while let Some(pos) = contents[from..].find(ANCHOR).map(|pos| from + pos) {
+        from = pos + ANCHOR.len();
+        let line_start = contents[..pos].rfind('\n').map_or(0, |nl| nl + 1);
+        if line_start < start {
+            continue;
+        }
+        if line_start > start || tocid.is_some() {
+            let text = &contents[start..line_start];
+            chunks.push(Chunk { tocid, word: headword(text), line: line.line, text });
+            line.advance(text, start);
+        }
+        start = line_start;
+        let hex = &contents[from..];
+        let hex = &hex[..hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len())];
+        tocid = u32::from_str_radix(hex, 16).ok();
+    }
+    if start < contents.len() || tocid.is_some() {
+        let text = &contents[start..];
+        chunks.push(Chunk { tocid, word: headword(text), line: line.line, text });
+    }
+    chunks
+}
+
+fn headword(text: &str) -> &str {
+    let from = match (text.find("<strong>"), text.find("<b>")) {
+        (Some(strong), Some(b)) if b < strong => b + "<b>".len(),
+        (Some(strong), _) => strong + "<strong>".len(),
+        (None, Some(b)) => b + "<b>".len(),
+        (None, None) => return "",
+    };
+    let word = &text[from..];
+    &word[..word.find('<').unwrap_or(word.len())]
+}
+
+/// Compare `old` and `new` entry by entry, matching entries by tocid, and return those that
+/// differ in the order they appear in `new` followed by those removed from `old`.
+pub fn diff_entries<'a>(old: &'a str, new: &'a str) -> Vec<EntryDiff<'a>> {
+    // the n-th occurrence of a tocid is matched with its n-th occurrence in the other document
+    fn keyed(chunks: Vec<Chunk<'_>>) -> Vec<((Option<u32>, usize), Chunk<'_>)> {
+        let mut seen = HashMap::new();
+        chunks.into_iter().map(|chunk| {
+            let nth = seen.entry(chunk.tocid).or_insert(0);
+            *nth += 1;
+            ((chunk.tocid, *nth), chunk)
+        }).collect()
+    }
+    let mut old_chunks: HashMap<_, _> = keyed(split_entries(old)).into_iter().collect();
+    let mut diffs = Vec::new();
+    for (key, chunk) in keyed(split_entries(new)) {
+        match old_chunks.remove(&key) {
+            Some(ref old_chunk) if old_chunk.text == chunk.text => (),
+            Some(old_chunk) => diffs.push(EntryDiff {
+                tocid: chunk.tocid,
+                word: chunk.word,
+                line: old_chunk.line,
+                old: old_chunk.text,
+                new: chunk.text,
+            }),
+            None => diffs.push(EntryDiff {
+                tocid: chunk.tocid,
+                word: chunk.word,
+                line: chunk.line,
+                old: "",
+                new: chunk.text,
+            }),
+        }
+    }
+    let mut removed: Vec<_> = old_chunks.into_values().collect();
+    removed.sort_by_key(|chunk| chunk.line);
+    diffs.extend(removed.into_iter().map(|chunk| EntryDiff {
+        tocid: chunk.tocid,
+        word: chunk.word,
+        line: chunk.line,
+        old: chunk.text,
+        new: "",
+    }));
+    diffs
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_entries, find, registry};
+
+    #[test]
+    fn registry_test() {
+        let ids: Vec<_> = registry().iter().map(|patch| patch.id()).collect();
+        assert_eq!(ids, ["004", "007", "018", "019", "g673-004"]);
+        assert!(find("019").is_some());
+        assert!(find("020").is_none());
+    }
+
+    #[test]
+    fn chain_test() {
+        let source = "<p id=\"MBP_TOC_1\"><big><b>Ab´bey</b></big>, <i>n.</i> \
+                      {<small><b>Ab´ba</b></small>, <i>n.</i>}</p>\n";
+        let mut log = Vec::new();
+        let mut contents = source.to_string();
+        for id in &["004", "007"] {
+            contents = find(id).unwrap().apply(&contents, &mut log);
+        }
+        assert_eq!(contents, "<div id=\"MBP_TOC_1\" data-ascii=\"Abbey\">\n\
+                              <p><strong>Ab´bey</strong>, <i>n.</i> </p>\n</div>\n\
+                              <div id=\"MBP_TOC_30000\" data-ascii=\"Abba\">\n\
+                              <p><strong>Ab´ba</strong>, <i>n.</i></p>\n</div>\n");
+        assert!(log.is_empty());
+
+        let diffs = diff_entries(source, &contents);
+        let touched: Vec<_> = diffs.iter().map(|d| (d.tocid, d.word, d.old.is_empty())).collect();
+        assert_eq!(touched, [(Some(1), "Ab´bey", false), (Some(0x30000), "Ab´ba", true)]);
+        assert_eq!(diffs[1].line_stat(), (3, 0));
+        assert_eq!(diff_entries(source, source).len(), 0);
+    }
+
+    #[test]
+    fn malformed_test() {
+        let mut log = Vec::new();
+        let mut nested = find("g673-004").unwrap();
+        assert_eq!(nested.apply("<hw><hw>abc", &mut log), "abc");
+        assert!(log.is_empty());
+        let unclosed = "<hw><hw>a</hw> b\n<hw><hw>c</hw>}\n";
+        assert_eq!(nested.apply(unclosed, &mut log), "<hw><hw>a</hw> b\n<hw>c</hw>\n");
+        assert_eq!(log.len(), 1);
+
+        let quoted = "<p id=\"MBP_TOC_1\"><big><b>a\"b</b></big>, <i>n.</i></p>\n\
+                      <p id=\"MBP_TOC_2\"><big><b>Ab´bey</b></big></p>\n";
+        assert_eq!(find("007").unwrap().apply(quoted, &mut log),
+                   "<p id=\"MBP_TOC_1\"><big><b>a\"b</b></big>, <i>n.</i></p>\n\
+                    <div id=\"MBP_TOC_2\" data-ascii=\"Abbey\">\n<p><strong>Ab´bey</strong></p>\n</div>\n");
+        assert_eq!(log.len(), 2);
+    }
+}
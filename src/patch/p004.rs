@@ -0,0 +1,86 @@
+use patch::Patch;
+use std::iter;
+use toc::TocAllocator;
+
+struct SmallWords<'a> {
+    word: &'a str,
+    alts: &'a str,
+}
+
+named!(small_words<&str, SmallWords<'_>>,
+       do_parse!(
+           word: delimited!(
+               tag!("<small><b>"),
+               take_until!("</b>"),
+               tag!("</b></small>")) >>
+           alts: take_until!("}") >>
+           ( SmallWords { word, alts } )
+      ));
+
+named!(small_words_in_curly<&str, SmallWords<'_>>,
+       delimited!(
+           ws!(tag!("{")),
+           small_words,
+           tag!("}")));
+
+named!(next<&str, &str>, take_until!("{"));
+
+named!(skip_line<&str, &str>, take_until!("\n"));
+
+/// Splits alternate forms given in curly braces, like `{<small><b>Word</b></small>, <i>n.</i>}`,
+/// out into entries of their own.
+pub struct CurlyAlternates {
+    /// Where the ids of the new entries come from; the ids in the patched document are added to it.
+    pub ids: TocAllocator,
+}
+
+impl Default for CurlyAlternates {
+    fn default() -> CurlyAlternates {
+        CurlyAlternates { ids: TocAllocator::new(iter::once(0x30000..0x40000)) }
+    }
+}
+
+impl Patch for CurlyAlternates {
+    fn id(&self) -> &'static str {
+        "004"
+    }
+
+    fn description(&self) -> &'static str {
+        "split curly-braced alternate forms into entries of their own"
+    }
+
+    fn apply(&mut self, mut contents: &str, log: &mut Vec<String>) -> String {
+        for tocid in self.ids.scan(contents) {
+            log.push(format!("duplicate id MBP_TOC_{:X}", tocid));
+        }
+        let mut patched = String::with_capacity(contents.len());
+        while let Ok((remaining, consumed)) = next(contents) {
+            patched.push_str(consumed);
+            if let Ok((after, words)) = small_words_in_curly(remaining) {
+                contents = after;
+                match self.ids.allocate() {
+                    Some(id) => patched.push_str(&format!("</p>\n<p id=\"MBP_TOC_{id:X}\"><big><b>{word}</b></big>{alts}",
+                                                          id = id, word = words.word, alts = words.alts)),
+                    None => {
+                        log.push(format!("no free tocid left for {:?}", words.word));
+                        patched.push_str(&remaining[..remaining.len() - after.len()]);
+                    }
+                }
+            } else if let Ok((remaining, line)) = skip_line(remaining) {
+                contents = remaining;
+                log.push(format!("badly formatted curly: {:?}", line));
+                patched.push_str(line);
+            } else {
+                contents = remaining;
+                log.push(format!("badly formatted curly in the final line: {:?}", remaining));
+                break;
+            }
+        }
+        patched.push_str(contents);
+        patched
+    }
+
+    fn toc_allocator(&mut self) -> Option<&mut TocAllocator> {
+        Some(&mut self.ids)
+    }
+}
@@ -0,0 +1,35 @@
+use parser::{self, RawEntry};
+use patch::{self, Patch};
+
+/// Converts entries from `<p id>` paragraphs to `<div id>` blocks carrying an ascii form of the
+/// headword.
+pub struct DivEntries;
+
+fn write_html(out: &mut String, entry: RawEntry, log: &mut Vec<String>) -> bool {
+    use std::fmt::Write;
+    let ascii_word = parser::word_to_ascii(entry.word).unwrap_or_else(|err| {
+        log.push(format!("{}: {}", entry.word, err));
+        parser::fold_to_ascii(entry.word)
+    });
+    if ascii_word.contains('"') {
+        log.push(format!("{}: '\"' in data-ascii, entry left unchanged", entry.word));
+        return false;
+    }
+    write!(out, "<div id=\"MBP_TOC_{id:X}\" data-ascii=\"{ascii}\">\n<p><strong>{word}</strong>{body}</p>{extras}</div>\n",
+           id = entry.tocid, ascii = ascii_word, word = entry.word, body = entry.body, extras = entry.extras).unwrap();
+    true
+}
+
+impl Patch for DivEntries {
+    fn id(&self) -> &'static str {
+        "007"
+    }
+
+    fn description(&self) -> &'static str {
+        "convert <p id> entries to <div id> blocks with a data-ascii headword"
+    }
+
+    fn apply(&mut self, contents: &str, log: &mut Vec<String>) -> String {
+        patch::patch_raw_entries(contents, log, write_html)
+    }
+}
@@ -0,0 +1,105 @@
+use parser::{ParaTag, SimpleTag, TaggedEntry};
+use patch::{self, Patch};
+use visit::Fold;
+
+/// Replaces word references inside grammar boxes with `<small><b>` text, unless preceded by "see"
+/// or "of" (or a comma continuing such a list).
+pub struct UnlinkGrammar;
+
+/// The abbreviations that made a box a grammar box when this patch was first applied. Boxes that
+/// `GrammarLabel` has recognised since, like `[<i>n.</i> ...]`, are left alone so that replaying
+/// the patch gives the same result.
+const LEGACY_MARKERS: &[&str] = &[
+    "compar.", "Compar.", "dat.", "imp.", "inf.", "nom.", "obj.", "obs.", "Obs.", "p. p.", "p. pr.",
+    "pl.", "poss.", "Poss.", "pres.", "pret.", "sing.", "Sing.", "superl.", "Superl.", "v. i.",
+    "v. t.", "vb. n.",
+];
+
+fn is_legacy_grammar(btags: &[SimpleTag]) -> bool {
+    match btags.first() {
+        Some(SimpleTag::Emph(text)) => LEGACY_MARKERS.iter().any(|marker| text.starts_with(marker)),
+        _ => false,
+    }
+}
+
+struct Unlink<'l> {
+    word: &'l str,
+    log: &'l mut Vec<String>,
+}
+
+impl<'l> Unlink<'l> {
+    fn unlink<'a>(&mut self, btags: Vec<SimpleTag<'a>>) -> Vec<SimpleTag<'a>> {
+        if !is_legacy_grammar(&btags) {
+            return btags;
+        }
+        let mut keep_ref = false;
+        btags.into_iter().map(|stag| match stag {
+            SimpleTag::Plain(text) => {
+                if keep_ref {
+                    keep_ref = text.trim() == ",";
+                } else {
+                    keep_ref = text.ends_with("See ") || text.ends_with("see ") ||
+                               text.ends_with("of ");
+                }
+                SimpleTag::Plain(text)
+            }
+            SimpleTag::WordRef(tocid, text) => {
+                if !keep_ref {
+                    SimpleTag::SmallB(text)
+                } else {
+                    self.log.push(format!("kept ref to {} in {}", text, self.word));
+                    SimpleTag::WordRef(tocid, text)
+                }
+            }
+            stag => {
+                keep_ref = false;
+                stag
+            }
+        }).collect()
+    }
+}
+
+impl<'a, 'l> Fold<'a> for Unlink<'l> {
+    fn fold_boxed(&mut self, btags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::Boxed(self.unlink(btags))
+    }
+
+    fn fold_boxed_grammar(&mut self, btags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::BoxedGrammar(self.unlink(btags))
+    }
+}
+
+fn patch_entry<'a>(entry: TaggedEntry<'a>, log: &mut Vec<String>) -> TaggedEntry<'a> {
+    let word = entry.word.to_string();
+    Unlink { word: &word, log }.fold_entry(entry)
+}
+
+impl Patch for UnlinkGrammar {
+    fn id(&self) -> &'static str {
+        "018"
+    }
+
+    fn description(&self) -> &'static str {
+        "unlink word references in grammar boxes unless preceded by \"see\" or \"of\""
+    }
+
+    fn apply(&mut self, contents: &str, log: &mut Vec<String>) -> String {
+        patch::patch_entries(contents, log, patch_entry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use patch::find;
+
+    #[test]
+    fn legacy_markers_test() {
+        let contents = "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p><strong>A</strong> \
+                        [<i>n.</i> <a href=\"#MBP_TOC_B\">Foo</a>] \
+                        [<i>pl.</i> <a href=\"#MBP_TOC_C\">As</a>]</p>\n</div>\n";
+        let mut log = Vec::new();
+        assert_eq!(find("018").unwrap().apply(contents, &mut log),
+                   contents.replace("<a href=\"#MBP_TOC_C\">As</a>", "<small><b>As</b></small>"));
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use parser::{EntryTag, ParaTag, SimpleTag, TaggedEntry};
+use patch::{self, Patch};
+use visit::Fold;
+
+/// Splits quotations followed by an author out of paragraphs into blockquotes.
+pub struct QuotesToBlockquotes;
+
+enum PatchState<'a> {
+    WaitingDquotes(Vec<ParaTag<'a>>),
+    WaitingAuthor(Vec<SimpleTag<'a>>),
+}
+
+fn process_ptag<'a>(word: &str, log: &mut Vec<String>, out: &mut Vec<EntryTag<'a>>,
+                    state: PatchState<'a>, ptag: ParaTag<'a>) -> PatchState<'a> {
+    match state {
+        PatchState::WaitingDquotes(mut ptags_patched) => {
+            match ptag {
+                ParaTag::Dquotes(mut qstags) => {
+                    out.push(EntryTag::Para(ptags_patched));
+                    let last_idx = qstags.len()-1;
+                    let author_len = match qstags[..] {
+                        [.., SimpleTag::BreakTag, SimpleTag::Emph(_)] => 2,
+                        [.., SimpleTag::Plain(ref prec), SimpleTag::Emph(ref author)]
+                            if prec.ends_with(' ') && author.ends_with('.') => 1,
+                        _ => 0,
+                    };
+                    if author_len > 0 {
+                        let author = match qstags.pop() {
+                            Some(SimpleTag::Emph(author)) => author,
+                            _ => unreachable!("this can't be!"),
+                        };
+                        qstags.truncate(last_idx + 1 - author_len);
+                        out.push(EntryTag::Blockquote(qstags, Some(author)));
+                        PatchState::WaitingDquotes(Vec::new())
+                    } else {
+                        PatchState::WaitingAuthor(qstags)
+                    }
+                }
+                ptag => {
+                    ptags_patched.push(ptag);
+                    PatchState::WaitingDquotes(ptags_patched)
+                }
+            }
+        }
+        PatchState::WaitingAuthor(qstags) => {
+            let mut author_opt = None;
+            let mut ptag = ptag;
+            if let ParaTag::Simple(ref mut stags) = ptag {
+                author_opt = match stags[..] {
+                    [SimpleTag::BreakTag, SimpleTag::Emph(ref author), ..] => Some(author.clone()),
+                    [SimpleTag::Plain(ref space), SimpleTag::Emph(ref author), ..] if space == " " =>
+                        Some(author.clone()),
+                    _ => None,
+                };
+                if let Some(ref author) = author_opt {
+                    log.push(format!("Author `{}`", author));
+                }
+                if author_opt.is_some() {
+                    stags.drain(0..2);
+                }
+            }
+            if author_opt.is_none() {
+                log.push(format!("No author in {} around `{}`", word, qstags[0]));
+            }
+            out.push(EntryTag::Blockquote(qstags, author_opt));
+            process_ptag(word, log, out, PatchState::WaitingDquotes(Vec::new()), ptag)
+        }
+    }
+}
+
+struct Split<'l> {
+    word: &'l str,
+    log: &'l mut Vec<String>,
+}
+
+impl<'a, 'l> Fold<'a> for Split<'l> {
+    fn fold_para(&mut self, ptags: Vec<ParaTag<'a>>) -> Vec<EntryTag<'a>> {
+        let mut out = Vec::new();
+        let mut patch_state = PatchState::WaitingDquotes(Vec::new());
+        for ptag in ptags {
+            patch_state = process_ptag(self.word, self.log, &mut out, patch_state, ptag);
+        }
+        match patch_state {
+            PatchState::WaitingDquotes(ptags_patched) => {
+                if !ptags_patched.is_empty() {
+                    out.push(EntryTag::Para(ptags_patched));
+                }
+            }
+            PatchState::WaitingAuthor(qstags) => {
+                self.log.push(format!("No author in {} around `{}`", self.word, qstags[0]));
+                out.push(EntryTag::Blockquote(qstags, None));
+            }
+        }
+        out
+    }
+}
+
+fn patch_entry<'a>(entry: TaggedEntry<'a>, log: &mut Vec<String>) -> TaggedEntry<'a> {
+    let word = entry.word.to_string();
+    Split { word: &word, log }.fold_entry(entry)
+}
+
+impl Patch for QuotesToBlockquotes {
+    fn id(&self) -> &'static str {
+        "019"
+    }
+
+    fn description(&self) -> &'static str {
+        "move quotations followed by an author out of paragraphs into blockquotes"
+    }
+
+    fn apply(&mut self, contents: &str, log: &mut Vec<String>) -> String {
+        patch::patch_entries(contents, log, patch_entry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::patch_entry;
+    use parser::{EntryTag, Parser};
+
+    #[test]
+    fn split_test() {
+        let contents = "<div id=\"MBP_TOC_1\" data-ascii=\"A\">\n<p><strong>A</strong> \u{201C}<i>a</i>\u{201D}</p>\n\
+                        <p>\u{201C}Abide\u{201D}</p>\n<p>\u{201C}Abide with me.\u{201D} <i>Lyte.</i> More.</p>\n</div>\n";
+        let entry = Parser::new(contents).next().unwrap().1.unwrap().into_owned();
+        let mut log = Vec::new();
+        let patched = patch_entry(entry, &mut log);
+        let authors: Vec<_> = patched.tags.iter().filter_map(|tag| match *tag {
+            EntryTag::Blockquote(_, ref author) => Some(author.as_ref().map(|author| author.to_string())),
+            _ => None,
+        }).collect();
+        assert_eq!(authors, [None, None, Some("Lyte.".to_string())]);
+    }
+}
@@ -0,0 +1,380 @@
+//! The graph of `<a href="#MBP_TOC_x">` cross-references between entries.
+
+use fuzzy;
+use parser::{EntryTag, ParaTag, ParseError, Parser, SimpleTag, Spans, TaggedEntry};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+use std::ops::Range;
+use visit::Visit;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub tocid: u32,
+    /// The `data-ascii` form of the headword.
+    pub word: String,
+    /// Index of the file in `RefGraph::files`.
+    pub file: usize,
+    /// 1-based line of the entry in its file.
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub from: u32,
+    pub to: u32,
+    pub text: String,
+    /// Index of the file in `RefGraph::files`.
+    pub file: usize,
+    /// 1-based line of the linking entry in its file.
+    pub line: usize,
+}
+
+/// How to repair a link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repair {
+    /// Point the link at the entry with this tocid, whose headword matches the link text.
+    Retarget(u32),
+    /// Replace the link by its text.
+    Unlink,
+}
+
+#[derive(Debug, Default)]
+pub struct RefGraph {
+    pub files: Vec<String>,
+    /// Entries by tocid; the first one wins if a tocid occurs more than once.
+    pub nodes: BTreeMap<u32, Node>,
+    pub links: Vec<Link>,
+    by_word: HashMap<String, Vec<u32>>,
+}
+
+#[derive(Debug, Default)]
+pub struct RefReport<'g> {
+    /// Links to tocids no entry has.
+    pub dangling: Vec<&'g Link>,
+    pub self_links: Vec<&'g Link>,
+    /// Links whose text differs from the headword of their target.
+    pub mismatches: Vec<&'g Link>,
+    /// Entries no other entry links to.
+    pub orphans: Vec<&'g Node>,
+}
+
+/// Headword comparison key: case, diacritics, stress marks and punctuation are ignored, so that
+/// `"A•mœ´ba"` matches the `data-ascii` word `"Amoeba"`.
+fn word_key(word: &str) -> String {
+    fuzzy::key(word)
+}
+
+struct LinkCollector<'g> {
+    links: &'g mut Vec<Link>,
+    from: u32,
+    file: usize,
+    line: usize,
+}
+
+impl<'g> Visit for LinkCollector<'g> {
+    fn visit_word_ref(&mut self, tocid: u32, text: &str) {
+        self.links.push(Link {
+            from: self.from,
+            to: tocid,
+            text: text.to_string(),
+            file: self.file,
+            line: self.line,
+        });
+    }
+}
+
+impl RefGraph {
+    pub fn new() -> RefGraph {
+        RefGraph::default()
+    }
+
+    /// Add the entries of a file and the links they contain, returning the entries that failed to
+    /// parse.
+    pub fn add_file<'a>(&mut self, name: &str, contents: &'a str) -> Vec<ParseError<'a>> {
+        let file = self.files.len();
+        self.files.push(name.to_string());
+        let mut errors = Vec::new();
+        let mut parser = Parser::new(contents);
+        while let Some((_, entry)) = parser.next() {
+            match entry {
+                Ok(entry) => self.add_entry(&entry, file, parser.entry_line()),
+                Err(err) => errors.push(err),
+            }
+        }
+        errors
+    }
+
+    fn add_entry(&mut self, entry: &TaggedEntry, file: usize, line: usize) {
+        if let Entry::Vacant(node) = self.nodes.entry(entry.tocid) {
+            node.insert(Node { tocid: entry.tocid, word: entry.word.to_string(), file, line });
+            self.by_word.entry(word_key(&entry.word)).or_default().push(entry.tocid);
+        }
+        LinkCollector { links: &mut self.links, from: entry.tocid, file, line }.visit_entry(entry);
+    }
+
+    /// The only entry whose headword is `text`, if there is exactly one.
+    fn resolve(&self, text: &str) -> Option<u32> {
+        match self.by_word.get(&word_key(text)).map(|ids| &ids[..]) {
+            Some(&[tocid]) => Some(tocid),
+            _ => None,
+        }
+    }
+
+    /// Tocids of the entries whose headword is `word`.
+    pub fn find_word(&self, word: &str) -> &[u32] {
+        self.by_word.get(&word_key(word)).map_or(&[], |ids| &ids[..])
+    }
+
+    /// Distinct links between existing entries, self-links excluded.
+    pub fn edges(&self) -> BTreeSet<(u32, u32)> {
+        self.links.iter()
+            .filter(|link| link.from != link.to && self.nodes.contains_key(&link.to))
+            .map(|link| (link.from, link.to))
+            .collect()
+    }
+
+    /// The entries within `hops` links of `start`, following links in either direction.
+    pub fn neighborhood(&self, start: &[u32], hops: usize) -> BTreeSet<u32> {
+        let mut adjacent: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (from, to) in self.edges() {
+            adjacent.entry(from).or_default().push(to);
+            adjacent.entry(to).or_default().push(from);
+        }
+        let mut reached: BTreeSet<u32> = start.iter().cloned().collect();
+        let mut frontier: Vec<u32> = start.to_vec();
+        for _ in 0..hops {
+            let mut next = Vec::new();
+            for tocid in frontier {
+                for &other in adjacent.get(&tocid).map_or(&[][..], |ids| &ids[..]) {
+                    if reached.insert(other) {
+                        next.push(other);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        reached
+    }
+
+    fn subgraph(&self, only: Option<&BTreeSet<u32>>) -> (Vec<&Node>, Vec<(u32, u32)>) {
+        let keep = |tocid: &u32| only.is_none_or(|only| only.contains(tocid));
+        let nodes = self.nodes.values().filter(|node| keep(&node.tocid)).collect();
+        let edges = self.edges().into_iter().filter(|&(from, to)| keep(&from) && keep(&to)).collect();
+        (nodes, edges)
+    }
+
+    /// Write the graph, or the part of it between the entries in `only`, in Graphviz DOT.
+    pub fn write_dot<W: Write>(&self, mut out: W, only: Option<&BTreeSet<u32>>) -> io::Result<()> {
+        let (nodes, edges) = self.subgraph(only);
+        writeln!(out, "digraph refs {{")?;
+        for node in nodes {
+            let label = node.word.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(out, "  n{:X} [label=\"{}\"];", node.tocid, label)?;
+        }
+        for (from, to) in edges {
+            writeln!(out, "  n{:X} -> n{:X};", from, to)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Write the graph, or the part of it between the entries in `only`, in GraphML.
+    pub fn write_graphml<W: Write>(&self, mut out: W, only: Option<&BTreeSet<u32>>) -> io::Result<()> {
+        let (nodes, edges) = self.subgraph(only);
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        writeln!(out, "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>")?;
+        writeln!(out, "  <graph id=\"refs\" edgedefault=\"directed\">")?;
+        for node in nodes {
+            let label = node.word.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+                .replace('"', "&quot;");
+            writeln!(out, "    <node id=\"n{:X}\"><data key=\"label\">{}</data></node>", node.tocid, label)?;
+        }
+        for (from, to) in edges {
+            writeln!(out, "    <edge source=\"n{:X}\" target=\"n{:X}\"/>", from, to)?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    pub fn report(&self) -> RefReport<'_> {
+        let mut report = RefReport::default();
+        let mut linked = HashSet::new();
+        for link in &self.links {
+            if link.from == link.to {
+                report.self_links.push(link);
+                continue;
+            }
+            linked.insert(link.to);
+            match self.nodes.get(&link.to) {
+                None => report.dangling.push(link),
+                Some(node) if word_key(&node.word) != word_key(&link.text) => report.mismatches.push(link),
+                Some(_) => (),
+            }
+        }
+        report.orphans = self.nodes.values().filter(|node| !linked.contains(&node.tocid)).collect();
+        report
+    }
+
+    /// How to repair a link from `from` to `to` with the given text, if it is dangling or a
+    /// self-link and the repair is unambiguous. Self-links are unlinked unless their text names
+    /// another entry. Links whose text merely differs from their target's headword, like an
+    /// inflected form linking to its lemma, are reported but never repaired.
+    pub fn repair(&self, from: u32, to: u32, text: &str) -> Option<Repair> {
+        let resolved = self.resolve(text).filter(|&tocid| tocid != from);
+        if from == to {
+            Some(resolved.map_or(Repair::Unlink, Repair::Retarget))
+        } else if self.nodes.contains_key(&to) {
+            None
+        } else {
+            resolved.map(Repair::Retarget)
+        }
+    }
+}
+
+/// The links of an entry with their byte ranges, given the spans of the entry.
+fn spanned_links<'e>(entry: &'e TaggedEntry, spans: &Spans) -> Vec<(u32, &'e str, Range<usize>)> {
+    let mut links = Vec::new();
+    let mut push = |tags: &'e [SimpleTag], spans: &[Spans]| {
+        for (tag, spans) in tags.iter().zip(spans) {
+            if let SimpleTag::WordRef(to, ref text) = *tag {
+                links.push((to, &text[..], spans.span.clone()));
+            }
+        }
+    };
+    for (tag, spans) in entry.tags.iter().zip(&spans.children) {
+        match *tag {
+            EntryTag::Blockquote(ref tags, _) => push(tags, &spans.children),
+            EntryTag::Para(ref ptags) => for (ptag, spans) in ptags.iter().zip(&spans.children) {
+                match *ptag {
+                    ParaTag::Boxed(ref tags) | ParaTag::BoxedGrammar(ref tags) | ParaTag::Dquotes(ref tags)
+                        | ParaTag::Simple(ref tags) => push(tags, &spans.children),
+                    ParaTag::Strong(_) => (),
+                }
+            },
+            _ => (),
+        }
+    }
+    links
+}
+
+/// Repair the links of `contents` that `graph` knows how to fix, returning the patched text and
+/// the number of links repaired. Only the repaired links are rewritten; everything else, including
+/// entries that fail to parse, is copied through as it is. Parse failures are logged.
+pub fn fix_links(graph: &RefGraph, contents: &str, log: &mut Vec<String>) -> (String, usize) {
+    let mut patched = String::with_capacity(contents.len());
+    let mut copied = 0;
+    let mut fixed = 0;
+    for (_, entry) in Parser::new(contents).spanned() {
+        let (entry, spans) = match entry {
+            Ok(spanned) => spanned,
+            Err(err) => {
+                log.push(err.to_string());
+                continue;
+            }
+        };
+        let from = entry.tocid;
+        for (to, text, span) in spanned_links(&entry, &spans) {
+            let repaired = match graph.repair(from, to, text) {
+                Some(Repair::Retarget(tocid)) => {
+                    log.push(format!("MBP_TOC_{:X}: link {:?} retargeted from MBP_TOC_{:X} to MBP_TOC_{:X}",
+                                     from, text, to, tocid));
+                    SimpleTag::WordRef(tocid, text.into()).to_string()
+                }
+                Some(Repair::Unlink) => {
+                    log.push(format!("MBP_TOC_{:X}: self-link {:?} unlinked", from, text));
+                    text.to_string()
+                }
+                None => continue,
+            };
+            fixed += 1;
+            patched.push_str(&contents[copied..span.start]);
+            patched.push_str(&repaired);
+            copied = span.end;
+        }
+    }
+    patched.push_str(&contents[copied..]);
+    (patched, fixed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fix_links, RefGraph, Repair};
+
+    const CONTENTS: &str = "<div id=\"MBP_TOC_A\" data-ascii=\"Abide\">\n<p><strong>A•bide´</strong> \
+                            See <a href=\"#MBP_TOC_B\">Abode</a>, <a href=\"#MBP_TOC_A\">Abide</a>.</p>\n</div>\n\
+                            <div id=\"MBP_TOC_B\" data-ascii=\"Abode\">\n<p><strong>A•bode´</strong> \
+                            See <a href=\"#MBP_TOC_F\">A•bide´</a>, <a href=\"#MBP_TOC_A\">Bide</a>.</p>\n</div>\n\
+                            <div id=\"MBP_TOC_C\" data-ascii=\"Abed\">\n<p><strong>A•bed´</strong></p>\n</div>\n";
+
+    #[test]
+    fn report_test() {
+        let mut graph = RefGraph::new();
+        assert!(graph.add_file("a.html", CONTENTS).is_empty());
+        assert_eq!(graph.links.len(), 4);
+        let report = graph.report();
+        let ids = |links: &[&super::Link]| links.iter().map(|l| (l.from, l.to)).collect::<Vec<_>>();
+        assert_eq!(ids(&report.dangling), [(0xB, 0xF)]);
+        assert_eq!(ids(&report.self_links), [(0xA, 0xA)]);
+        assert_eq!(ids(&report.mismatches), [(0xB, 0xA)]);
+        assert_eq!(report.orphans.iter().map(|n| n.tocid).collect::<Vec<_>>(), [0xC]);
+        assert_eq!(report.dangling[0].line, 4);
+
+        assert_eq!(graph.repair(0xA, 0xA, "Abide"), Some(Repair::Unlink));
+        assert_eq!(graph.repair(0xB, 0xF, "A•bide´"), Some(Repair::Retarget(0xA)));
+        assert_eq!(graph.repair(0xB, 0xA, "Bide"), None);
+        assert_eq!(graph.repair(0xA, 0xB, "Abode"), None);
+
+        graph.add_file("b.html", "<div id=\"MBP_TOC_D\" data-ascii=\"Amoeba\">\n\
+                                  <p><strong>A•mœ´ba</strong></p>\n</div>\n");
+        assert_eq!(graph.repair(0xA, 0xD, "A•mœ´ba"), None);
+        assert_eq!(graph.find_word("Amœba"), [0xD]);
+    }
+
+    #[test]
+    fn export_test() {
+        let mut graph = RefGraph::new();
+        graph.add_file("a.html", CONTENTS);
+        assert_eq!(graph.find_word("abode"), [0xB]);
+        assert_eq!(graph.neighborhood(&[0xB], 1).into_iter().collect::<Vec<_>>(), [0xA, 0xB]);
+        assert_eq!(graph.neighborhood(&[0xC], 3).len(), 1);
+        let mut dot = Vec::new();
+        graph.write_dot(&mut dot, None).unwrap();
+        assert_eq!(String::from_utf8(dot).unwrap(),
+                   "digraph refs {\n  nA [label=\"Abide\"];\n  nB [label=\"Abode\"];\n  \
+                    nC [label=\"Abed\"];\n  nA -> nB;\n  nB -> nA;\n}\n");
+        let mut graphml = Vec::new();
+        graph.write_graphml(&mut graphml, Some(&graph.neighborhood(&[0xC], 1))).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert!(graphml.contains("<node id=\"nC\"><data key=\"label\">Abed</data></node>"));
+        assert!(!graphml.contains("<edge"));
+    }
+
+    #[test]
+    fn fix_links_test() {
+        let mut graph = RefGraph::new();
+        graph.add_file("a.html", CONTENTS);
+        let mut log = Vec::new();
+        let (fixed, count) = fix_links(&graph, CONTENTS, &mut log);
+        assert_eq!((count, log.len()), (2, 2));
+        assert_eq!(fixed, CONTENTS.replace("<a href=\"#MBP_TOC_A\">Abide</a>", "Abide")
+                                  .replace("#MBP_TOC_F", "#MBP_TOC_A"));
+
+        // untouched entries and links are copied as they are, not as they would render
+        let padded = CONTENTS.replace("MBP_TOC_C\"", "MBP_TOC_0C\"").replace("#MBP_TOC_B\"", "#MBP_TOC_0B\"");
+        let (fixed, count) = fix_links(&graph, &padded, &mut log);
+        assert_eq!(count, 2);
+        assert!(fixed.contains("<div id=\"MBP_TOC_0C\"") && fixed.contains("<a href=\"#MBP_TOC_0B\">Abode</a>"));
+
+        // a link from an inflected form to its lemma is a mismatch, but a valid one
+        let inflected = CONTENTS.replace("<p><strong>A•bed´</strong></p>",
+                                         "<p><strong>A•bed´</strong> See <a href=\"#MBP_TOC_A\">Abode</a>.</p>");
+        let mut graph = RefGraph::new();
+        graph.add_file("a.html", &inflected);
+        assert_eq!(graph.report().mismatches.iter().map(|l| (l.from, l.to)).collect::<Vec<_>>(),
+                   [(0xB, 0xA), (0xC, 0xA)]);
+        let (fixed, count) = fix_links(&graph, &inflected, &mut log);
+        assert_eq!(count, 2);
+        assert!(fixed.contains("<strong>A•bed´</strong> See <a href=\"#MBP_TOC_A\">Abode</a>."));
+    }
+}
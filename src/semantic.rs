@@ -0,0 +1,327 @@
+//! Lexicographic model of an entry, lifted from its tag tree.
+//!
+//! The first paragraph of an entry opens with its headword in `<strong>`, followed by the part of
+//! speech in italics, grammar boxes (`[<i>pl.</i> …]`) and an etymology box. Definitions follow,
+//! numbered by bold `<b>1.</b>` markers, and blockquotes cite uses of the preceding sense.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use grammar::{GrammarLabel, LabelKind};
+use parser::{strip_stress, EntryTag, ParaTag, SimpleTag, TaggedEntry};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    pub tocid: u32,
+    /// The headword without stress and syllable marks.
+    pub headword: String,
+    pub syllables: Vec<Syllable>,
+    /// Part of speech as abbreviated in the source, like `"v. t."`.
+    pub pos: Option<String>,
+    /// Contents of the grammar boxes, like `"pl. Abodes"`.
+    pub grammar: Vec<String>,
+    pub etymology: Option<String>,
+    pub senses: Vec<Sense>,
+    pub quotes: Vec<Quote>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Syllable {
+    pub text: String,
+    pub stressed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sense {
+    /// The sense marker, like `"1."` or `"(a)"`; `None` for an unnumbered definition.
+    pub number: Option<String>,
+    /// Parenthesized usage labels opening the definition, like `"(Law)"`.
+    pub labels: Vec<String>,
+    pub definition: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Quote {
+    pub text: String,
+    pub author: Option<String>,
+    /// Index of the sense the quote follows in `Entry::senses`.
+    pub sense: Option<usize>,
+}
+
+/// A part of an entry that fits nowhere in the model.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Unclassified {
+    /// What kind of tag it was, like `"pre"` or `"strong"`.
+    pub kind: &'static str,
+    pub text: String,
+}
+
+impl Entry {
+    /// Lift `entry` into the model, along with everything that could not be classified.
+    pub fn lift(entry: &TaggedEntry) -> (Entry, Vec<Unclassified>) {
+        let mut lifter = Lifter {
+            entry: Entry {
+                tocid: entry.tocid,
+                headword: String::new(),
+                syllables: Vec::new(),
+                pos: None,
+                grammar: Vec::new(),
+                etymology: None,
+                senses: Vec::new(),
+                quotes: Vec::new(),
+            },
+            unclassified: Vec::new(),
+            in_header: true,
+        };
+        for tag in &entry.tags {
+            lifter.entry_tag(tag);
+        }
+        if lifter.entry.headword.is_empty() {
+            lifter.entry.headword = entry.word.to_string();
+            lifter.report("headword", "");
+        }
+        for sense in &mut lifter.entry.senses {
+            let len = sense.definition.trim_end().len();
+            sense.definition.truncate(len);
+        }
+        (lifter.entry, lifter.unclassified)
+    }
+
+    /// The parts of speech given by `pos`, like `[VerbTransitive, VerbIntransitive]` for
+    /// `"v. t. & i."`.
+    pub fn part_of_speech(&self) -> Vec<GrammarLabel> {
+        let pos = self.pos.as_ref().map_or("", |pos| &pos[..]);
+        GrammarLabel::parse_prefix(pos).0.into_iter()
+            .filter(|label| label.kind() == LabelKind::PartOfSpeech)
+            .collect()
+    }
+}
+
+pub fn syllables(word: &str) -> Vec<Syllable> {
+    let mut syllables = Vec::new();
+    let mut text = String::new();
+    for c in word.chars() {
+        match c {
+            '•' | '´' | ' ' if !text.is_empty() => {
+                syllables.push(Syllable { text: text.clone(), stressed: c == '´' });
+                text.clear();
+            }
+            '•' | '´' | ' ' => (),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        syllables.push(Syllable { text, stressed: false });
+    }
+    syllables
+}
+
+fn is_sense_number(text: &str) -> bool {
+    let text = text.trim();
+    let number = if text.starts_with('(') && text.ends_with(')') && text.len() > 2 {
+        &text[1..text.len() - 1]
+    } else if let Some(number) = text.strip_suffix('.') {
+        number
+    } else {
+        return false;
+    };
+    !number.is_empty() &&
+        (number.chars().all(|c| c.is_ascii_digit()) ||
+         number.len() == 1 && number.chars().all(|c| c.is_ascii_lowercase()))
+}
+
+/// Whether `text` only separates the parts of the header, like `"(?), "` does after a headword
+/// whose pronunciation was not transcribed.
+fn is_separator(text: &str) -> bool {
+    text.replace("(?)", "").chars().all(|c| c.is_whitespace() || c == ',' || c == ';')
+}
+
+struct Lifter {
+    entry: Entry,
+    unclassified: Vec<Unclassified>,
+    /// Whether the definitions have not started yet.
+    in_header: bool,
+}
+
+impl Lifter {
+    fn report(&mut self, kind: &'static str, text: &str) {
+        self.unclassified.push(Unclassified { kind, text: text.to_string() });
+    }
+
+    fn text(&mut self, tags: &[SimpleTag]) -> String {
+        let mut text = String::new();
+        for tag in tags {
+            self.push_text(&mut text, tag);
+        }
+        text
+    }
+
+    fn push_text(&mut self, out: &mut String, tag: &SimpleTag) {
+        match *tag {
+            SimpleTag::Bold(ref text) | SimpleTag::Emph(ref text) | SimpleTag::Plain(ref text) |
+            SimpleTag::SmallB(ref text) | SimpleTag::Sub(ref text) | SimpleTag::Sup(ref text) |
+            SimpleTag::WordRef(_, ref text) => out.push_str(text),
+            SimpleTag::BoxedPlain(ref text) => {
+                out.push('[');
+                out.push_str(text);
+                out.push(']');
+            }
+            SimpleTag::BreakTag => out.push(' '),
+            SimpleTag::Unknown(ref raw_html) => self.report("unknown", raw_html),
+        }
+    }
+
+    /// The sense definitions are currently added to, starting an unnumbered one if needed.
+    fn sense(&mut self) -> &mut Sense {
+        self.in_header = false;
+        if self.entry.senses.is_empty() {
+            self.entry.senses.push(Sense { number: None, labels: Vec::new(), definition: String::new() });
+        }
+        self.entry.senses.last_mut().unwrap()
+    }
+
+    fn push_definition(&mut self, mut text: &str) {
+        let sense = self.sense();
+        if sense.definition.is_empty() {
+            // parenthesized labels open a definition
+            while let Some(end) = text.trim_start().find(')') {
+                let label = &text.trim_start()[..end + 1];
+                if !label.starts_with('(') {
+                    break;
+                }
+                sense.labels.push(label.to_string());
+                text = &text.trim_start()[end + 1..];
+            }
+            sense.definition.push_str(text.trim_start());
+        } else {
+            sense.definition.push_str(text);
+        }
+    }
+
+    fn entry_tag(&mut self, tag: &EntryTag) {
+        match *tag {
+            EntryTag::Blockquote(ref tags, ref author) => {
+                let text = self.text(tags);
+                let sense = self.entry.senses.len().checked_sub(1);
+                self.entry.quotes.push(Quote {
+                    text: text.trim().to_string(),
+                    author: author.as_ref().map(|author| author.to_string()),
+                    sense,
+                });
+            }
+            EntryTag::Para(ref tags) => {
+                if let Some(sense) = self.entry.senses.last_mut() {
+                    if !sense.definition.is_empty() {
+                        sense.definition.push(' ');
+                    }
+                }
+                for tag in tags {
+                    self.para_tag(tag);
+                }
+            }
+            EntryTag::Pre(ref raw_html) => self.report("pre", raw_html),
+            EntryTag::LineBreak => (),
+            EntryTag::Unknown(ref raw_html) => self.report("unknown block", raw_html),
+        }
+    }
+
+    fn para_tag(&mut self, tag: &ParaTag) {
+        match *tag {
+            ParaTag::Strong(ref word) => {
+                if self.entry.headword.is_empty() {
+                    self.entry.headword = strip_stress(word);
+                    self.entry.syllables = syllables(word);
+                } else {
+                    self.report("strong", word);
+                }
+            }
+            ParaTag::BoxedGrammar(ref tags) => {
+                let text = self.text(tags);
+                self.entry.grammar.push(text);
+            }
+            ParaTag::Boxed(ref tags) => {
+                let text = self.text(tags);
+                if self.in_header && self.entry.etymology.is_none() {
+                    self.entry.etymology = Some(text);
+                } else if !self.entry.senses.is_empty() {
+                    self.push_definition(&format!("[{}]", text));
+                } else {
+                    self.report("boxed", &text);
+                }
+            }
+            ParaTag::Dquotes(ref tags) => {
+                let text = self.text(tags);
+                self.push_definition(&format!("\u{201C}{}\u{201D}", text));
+            }
+            ParaTag::Simple(ref tags) => {
+                for tag in tags {
+                    self.simple_tag(tag);
+                }
+            }
+        }
+    }
+
+    fn simple_tag(&mut self, tag: &SimpleTag) {
+        match *tag {
+            SimpleTag::Bold(ref text) if is_sense_number(text) => {
+                self.in_header = false;
+                self.entry.senses.push(Sense {
+                    number: Some(text.trim().to_string()),
+                    labels: Vec::new(),
+                    definition: String::new(),
+                });
+            }
+            SimpleTag::Emph(ref text) if self.in_header && self.entry.pos.is_none() &&
+                                         text.ends_with('.') => {
+                self.entry.pos = Some(text.to_string());
+            }
+            SimpleTag::Plain(ref text) if self.in_header && is_separator(text) => (),
+            SimpleTag::Unknown(ref raw_html) => self.report("unknown", raw_html),
+            ref tag => {
+                let mut text = String::new();
+                self.push_text(&mut text, tag);
+                self.push_definition(&text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Entry, Quote, Sense, Unclassified};
+    use grammar::GrammarLabel;
+    use parser::Parser;
+
+    #[test]
+    fn lift_test() {
+        let contents = "<div id=\"MBP_TOC_1A\" data-ascii=\"Abide\">\n<p><strong>A•bide´</strong> (?), \
+                        <i>v. i.</i> [<i>imp.</i> <a href=\"#MBP_TOC_1B\">Abode</a>] [AS. <i>\u{101}b\u{12B}dan</i>.] \
+                        <b>1.</b> To wait; to pause.</p>\n<blockquote>\n\
+                        <p>\u{201C}Abide\u{201D} with me.</p>\n\u{2015}<i>Lyte.</i></blockquote>\n\
+                        <p><b>2.</b> (Law) To dwell; <i>as</i>, to abide.</p>\n<pre>x</pre></div>\n";
+        let entry = Parser::new(contents).next().unwrap().1.unwrap();
+        let (entry, unclassified) = Entry::lift(&entry);
+        assert_eq!(entry.headword, "Abide");
+        let syllables: Vec<_> = entry.syllables.iter().map(|s| (&s.text[..], s.stressed)).collect();
+        assert_eq!(syllables, [("A", false), ("bide", true)]);
+        assert_eq!(entry.pos.as_ref().unwrap(), "v. i.");
+        assert_eq!(entry.part_of_speech(), [GrammarLabel::VerbIntransitive]);
+        assert_eq!(entry.grammar, ["imp. Abode"]);
+        assert_eq!(entry.etymology.as_ref().unwrap(), "AS. \u{101}b\u{12B}dan.");
+        assert_eq!(entry.senses, [
+            Sense { number: Some("1.".into()), labels: vec![], definition: "To wait; to pause.".into() },
+            Sense { number: Some("2.".into()), labels: vec!["(Law)".into()],
+                    definition: "To dwell; as, to abide.".into() },
+        ]);
+        assert_eq!(entry.quotes, [Quote {
+            text: "\u{201C}Abide\u{201D} with me.".into(),
+            author: Some("Lyte.".into()),
+            sense: Some(0),
+        }]);
+        assert_eq!(unclassified, [Unclassified { kind: "pre", text: "x".into() }]);
+    }
+}
@@ -0,0 +1,213 @@
+use parser::{self, LineStart, ParseError, ParseOptions, TaggedEntry};
+use std::io::{BufRead, Result};
+use std::mem;
+
+/// An entry read from a stream, along with the text preceding it.
+#[derive(Debug)]
+pub struct EntryBuf {
+    pub skipped: String,
+    pub text: String,
+    /// Where the entry starts in the stream.
+    pub start: Position,
+    options: ParseOptions,
+}
+
+/// A position in a stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// Byte offset.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, in chars.
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position { offset: 0, line: 1, column: 1 }
+    }
+}
+
+impl Position {
+    fn advance(&mut self, text: &str) {
+        self.offset += text.len();
+        match text.rfind('\n') {
+            Some(last) => {
+                self.line += text.matches('\n').count();
+                self.column = text[last + 1..].chars().count() + 1;
+            }
+            None => self.column += text.chars().count(),
+        }
+    }
+}
+
+impl EntryBuf {
+    /// Parse the entry. Error positions are those in the stream.
+    pub fn parse(&self) -> ::std::result::Result<TaggedEntry<'_>, ParseError<'_>> {
+        parser::parse_entry_str(&self.text, &self.text, LineStart::default(), self.options).map_err(|mut err| {
+            if err.line == 1 {
+                err.column += self.start.column - 1;
+            }
+            err.line += self.start.line - 1;
+            err.offset += self.start.offset;
+            err
+        })
+    }
+}
+
+/// Reads entries one at a time from a `BufRead`, buffering only the current entry.
+pub struct EntryReader<R> {
+    reader: R,
+    buf: String,
+    eof: bool,
+    /// Where the start of `buf` is in the stream.
+    position: Position,
+    options: ParseOptions,
+    max_skipped: Option<usize>,
+}
+
+const ENTRY_START: &str = "<div id=\"MBP_";
+const ENTRY_END: &str = "</div>\n";
+
+/// The largest char boundary of `text` at or before `idx`.
+fn floor_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+impl<R: BufRead> EntryReader<R> {
+    pub fn new(reader: R) -> EntryReader<R> {
+        EntryReader::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> EntryReader<R> {
+        EntryReader {
+            reader,
+            buf: String::new(),
+            eof: false,
+            position: Position::default(),
+            options,
+            max_skipped: None,
+        }
+    }
+
+    /// Keep only about the last `max` bytes of the text between entries, so that a long stretch
+    /// without entries is not buffered whole. `EntryBuf::skipped` and `remaining` are then
+    /// truncated at the front.
+    pub fn max_skipped(mut self, max: usize) -> EntryReader<R> {
+        self.max_skipped = Some(max);
+        self
+    }
+
+    /// Text following the last entry, once the iterator is exhausted.
+    pub fn remaining(&self) -> &str {
+        &self.buf
+    }
+
+    /// Read lines until one of `pats` is found at or after `from`, returning the offset of the
+    /// earliest match. Text searched without a match is dropped from the front of the buffer down
+    /// to `max_kept` bytes.
+    fn fill_until(&mut self, pats: &[&str], mut from: usize, max_kept: Option<usize>) -> Result<Option<usize>> {
+        let longest = pats.iter().map(|pat| pat.len()).max().unwrap_or(1);
+        loop {
+            if let Some(idx) = pats.iter().filter_map(|pat| self.buf[from..].find(pat)).min() {
+                return Ok(Some(from + idx));
+            }
+            if self.eof {
+                return Ok(None);
+            }
+            // only a match straddling the end of the buffer is left to find in what was searched
+            from = from.max(floor_boundary(&self.buf, self.buf.len().saturating_sub(longest - 1)));
+            if let Some(max) = max_kept {
+                let cut = floor_boundary(&self.buf, self.buf.len().saturating_sub(max)).min(from);
+                self.position.advance(&self.buf[..cut]);
+                self.buf.drain(..cut);
+                from -= cut;
+            }
+            self.eof = self.reader.read_line(&mut self.buf)? == 0;
+        }
+    }
+
+    fn read_entry(&mut self) -> Result<Option<EntryBuf>> {
+        let start = match self.fill_until(&[ENTRY_START], 0, self.max_skipped)? {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        // like `parser::split_next`, an entry missing its `</div>\n` ends where the next one starts
+        let end = match self.fill_until(&[ENTRY_END, ENTRY_START], start + 1, None)? {
+            Some(idx) if self.buf[idx..].starts_with(ENTRY_END) => idx + ENTRY_END.len(),
+            Some(idx) => idx,
+            None => self.buf.len(),
+        };
+        let rest = self.buf.split_off(end);
+        let text = self.buf.split_off(start);
+        let skipped = mem::replace(&mut self.buf, rest);
+        self.position.advance(&skipped);
+        let start = self.position;
+        self.position.advance(&text);
+        Ok(Some(EntryBuf { skipped, text, start, options: self.options }))
+    }
+}
+
+impl<R: BufRead> Iterator for EntryReader<R> {
+    type Item = Result<EntryBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EntryReader;
+    use parser::Parser;
+
+    #[test]
+    fn entry_reader_test() {
+        let contents = "<html>\n<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p><strong>A</strong></p>\n</div>\n\
+                        <hr><div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p><u>B</u></p>\n</div>\n</html>\n";
+        let mut reader = EntryReader::new(contents.as_bytes());
+        let entries: Vec<_> = reader.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((&entries[0].skipped[..], &entries[1].skipped[..]), ("<html>\n", "<hr>"));
+        assert_eq!(entries[0].parse().unwrap().to_string(), entries[0].text);
+        let err = entries[1].parse().unwrap_err();
+        let parsed_err = Parser::new(contents).nth(1).unwrap().1.unwrap_err();
+        assert_eq!(err.rule, "simple_tags");
+        assert_eq!((err.offset, err.line, err.column), (parsed_err.offset, parsed_err.line, parsed_err.column));
+        let offset = contents.find("<div id=\"MBP_TOC_B").unwrap();
+        assert_eq!(entries[1].start, super::Position { offset, line: 5, column: 5 });
+        assert_eq!(reader.remaining(), "</html>\n");
+
+        let parsed: Vec<_> = Parser::new(contents).map(|(_, e)| e.is_ok()).collect();
+        assert_eq!(parsed, [true, false]);
+
+        let padded = format!("{}{}", "<!-- padding -->\n".repeat(100), contents);
+        let mut reader = EntryReader::new(padded.as_bytes()).max_skipped(20);
+        let entry = reader.next().unwrap().unwrap();
+        assert!(entry.skipped.len() <= 20 && entry.skipped.ends_with("-->\n<html>\n"));
+        assert_eq!(entry.start.line, 102);
+        assert_eq!(entry.text, entries[0].text);
+        assert_eq!(reader.count(), 1);
+    }
+
+    #[test]
+    fn unclosed_test() {
+        let contents = "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p>A</p>\n\
+                        <div id=\"MBP_TOC_B\" data-ascii=\"B\">\n<p>B</p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"C\">\n<p>C</p>\n</div>\n";
+        let entries: Vec<_> = EntryReader::new(contents.as_bytes()).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), Parser::new(contents).count());
+        let err = entries[0].parse().unwrap_err();
+        assert_eq!((err.rule, err.entry), ("div_close", Parser::new(contents).next().unwrap().1.unwrap_err().entry));
+        assert_eq!(entries[1].parse().unwrap().tocid, 0xB);
+        assert_eq!(entries[2].parse().unwrap().tocid, 0xC);
+    }
+}
@@ -0,0 +1,135 @@
+//! Allocation of unused `MBP_TOC_x` ids for new entries.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+/// Hands out tocids no entry of the scanned corpus uses, from configured ranges.
+///
+/// Ids handed out are remembered, so saving and reloading the allocator keeps later runs from
+/// reusing them even before the entries they were given to are written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TocAllocator {
+    ranges: Vec<Range<u32>>,
+    allocated: BTreeSet<u32>,
+    seen: BTreeSet<u32>,
+}
+
+const ANCHORS: &[&str] = &["<div id=\"MBP_TOC_", "<p id=\"MBP_TOC_"];
+
+fn parse_hex(text: &str) -> Option<u32> {
+    let end = text.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(text.len());
+    u32::from_str_radix(&text[..end], 16).ok()
+}
+
+fn invalid_data(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("bad tocid state line {:?}", line))
+}
+
+impl TocAllocator {
+    /// An allocator handing out ids from `ranges`, in order.
+    pub fn new<I: IntoIterator<Item = Range<u32>>>(ranges: I) -> TocAllocator {
+        TocAllocator {
+            ranges: ranges.into_iter().collect(),
+            allocated: BTreeSet::new(),
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// Mark the ids of the `<div id>` and `<p id>` anchors in `contents` as used, returning those
+    /// that were already used, in `contents` or in a file scanned before.
+    pub fn scan(&mut self, contents: &str) -> Vec<u32> {
+        let mut duplicates = Vec::new();
+        for anchor in ANCHORS {
+            for (pos, _) in contents.match_indices(anchor) {
+                if let Some(tocid) = parse_hex(&contents[pos + anchor.len()..]) {
+                    if !self.seen.insert(tocid) {
+                        duplicates.push(tocid);
+                    }
+                }
+            }
+        }
+        duplicates.sort_unstable();
+        duplicates
+    }
+
+    /// Forget the scanned ids, keeping the ranges and the ids handed out, as `load` would.
+    pub fn forget_scanned(&mut self) {
+        self.seen.clear();
+    }
+
+    pub fn is_used(&self, tocid: u32) -> bool {
+        self.seen.contains(&tocid) || self.allocated.contains(&tocid)
+    }
+
+    /// The lowest unused id of the first range that has one.
+    pub fn allocate(&mut self) -> Option<u32> {
+        let found = self.ranges.iter()
+            .filter_map(|range| range.clone().find(|&tocid| !self.is_used(tocid)))
+            .next();
+        if let Some(tocid) = found {
+            self.allocated.insert(tocid);
+        }
+        found
+    }
+
+    /// Write the ranges and the ids handed out so far, one per line as hex.
+    pub fn save<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for range in &self.ranges {
+            writeln!(out, "range {:X} {:X}", range.start, range.end)?;
+        }
+        for tocid in &self.allocated {
+            writeln!(out, "allocated {:X}", tocid)?;
+        }
+        out.flush()
+    }
+
+    /// Read an allocator written by `save`. Scanned ids are not saved; scan the corpus again.
+    pub fn load<R: BufRead>(input: R) -> io::Result<TocAllocator> {
+        let mut allocator = TocAllocator::default();
+        for line in input.lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                [] => (),
+                ["range", start, end] => {
+                    let start = parse_hex(start).ok_or_else(|| invalid_data(&line))?;
+                    let end = parse_hex(end).ok_or_else(|| invalid_data(&line))?;
+                    allocator.ranges.push(start..end);
+                }
+                ["allocated", tocid] => {
+                    allocator.allocated.insert(parse_hex(tocid).ok_or_else(|| invalid_data(&line))?);
+                }
+                _ => return Err(invalid_data(&line)),
+            }
+        }
+        Ok(allocator)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TocAllocator;
+
+    #[test]
+    fn allocate_test() {
+        let mut toc = TocAllocator::new(vec![0x10..0x12, 0x30000..0x40000]);
+        let contents = "<div id=\"MBP_TOC_10\" data-ascii=\"A\">\n<p id=\"MBP_TOC_30000\">\n\
+                        <a href=\"#MBP_TOC_11\">B</a><div id=\"MBP_TOC_10\" data-ascii=\"A\">\n";
+        assert_eq!(toc.scan(contents), [0x10]);
+        assert_eq!(toc.scan("<div id=\"MBP_TOC_30000\">"), [0x30000]);
+        assert!(toc.is_used(0x30000) && !toc.is_used(0x11));
+        assert_eq!(toc.allocate(), Some(0x11));
+        assert_eq!(toc.allocate(), Some(0x30001));
+
+        let mut saved = Vec::new();
+        toc.save(&mut saved).unwrap();
+        assert_eq!(String::from_utf8(saved.clone()).unwrap(),
+                   "range 10 12\nrange 30000 40000\nallocated 11\nallocated 30001\n");
+        let mut loaded = TocAllocator::load(&saved[..]).unwrap();
+        assert_eq!(loaded.allocate(), Some(0x10));
+        loaded.scan(contents);
+        assert_eq!(loaded.allocate(), Some(0x30002));
+        assert!(TocAllocator::load(&b"range 10\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use diff;
+use parser::{ParseError, ParseOptions, Parser};
+use std::borrow::Cow;
+
+/// An entry that does not render back to the text it was parsed from.
+#[derive(Debug)]
+pub struct Mismatch<'a> {
+    pub tocid: u32,
+    pub word: Cow<'a, str>,
+    /// 1-based line of the entry in the source.
+    pub line: usize,
+    pub source: &'a str,
+    pub rendered: String,
+}
+
+impl<'a> Mismatch<'a> {
+    pub fn diff(&self) -> String {
+        diff::unified_diff(self.source, &self.rendered, 3)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RoundtripReport<'a> {
+    /// Number of entries parsed successfully.
+    pub entries: usize,
+    pub errors: Vec<ParseError<'a>>,
+    pub mismatches: Vec<Mismatch<'a>>,
+}
+
+/// Parse every entry of `contents` and compare its rendering with its source text.
+pub fn verify_roundtrip(contents: &str, options: ParseOptions) -> RoundtripReport<'_> {
+    let mut report = RoundtripReport::default();
+    let mut parser = Parser::with_options(contents, options);
+    loop {
+        let before = parser.remaining();
+        let (skipped, entry) = match parser.next() {
+            Some(item) => item,
+            None => break,
+        };
+        let source = &before[skipped.len()..before.len() - parser.remaining().len()];
+        match entry {
+            Ok(entry) => {
+                report.entries += 1;
+                let rendered = entry.to_string();
+                if rendered != source {
+                    report.mismatches.push(Mismatch {
+                        tocid: entry.tocid,
+                        word: entry.word,
+                        line: parser.entry_line(),
+                        source,
+                        rendered,
+                    });
+                }
+            }
+            Err(err) => report.errors.push(err),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_roundtrip;
+    use parser::ParseOptions;
+
+    #[test]
+    fn verify_roundtrip_test() {
+        let contents = "<html>\n<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p>[] [<i>pl.</i> As]</p>\n</div>\n\
+                        <div id=\"MBP_TOC_0B\" data-ascii=\"B\">\n<p>B</p>\n</div>\n\
+                        <div id=\"MBP_TOC_C\" data-ascii=\"C\">\n<p><u>C</u></p>\n</div>\n";
+        let report = verify_roundtrip(contents, ParseOptions::default());
+        assert_eq!((report.entries, report.errors.len(), report.mismatches.len()), (2, 1, 1));
+        let mismatch = &report.mismatches[0];
+        assert_eq!((mismatch.tocid, &mismatch.word[..], mismatch.line), (0xB, "B", 5));
+        assert_eq!(mismatch.diff(), "@@ -1,3 +1,3 @@\n\
+                                     -<div id=\"MBP_TOC_0B\" data-ascii=\"B\">\n\
+                                     +<div id=\"MBP_TOC_B\" data-ascii=\"B\">\n <p>B</p>\n </div>\n");
+
+        let report = verify_roundtrip(contents, ParseOptions { lenient: true });
+        assert_eq!((report.entries, report.errors.len(), report.mismatches.len()), (3, 0, 1));
+    }
+}
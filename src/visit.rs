@@ -0,0 +1,246 @@
+//! Traversals of the tag tree of an entry.
+//!
+//! `Visit` walks an entry by reference; `Fold` consumes it and builds a new one. Both have a
+//! method per tag variant that by default just recurses into its inner tags, so implementors only
+//! override the variants they care about. The `walk_*` functions perform the default recursion
+//! and can be called from overriding methods.
+
+use parser::{EntryTag, ParaTag, SimpleTag, TaggedEntry};
+use std::borrow::Cow;
+
+pub trait Visit {
+    fn visit_entry(&mut self, entry: &TaggedEntry) {
+        walk_entry(self, entry)
+    }
+
+    fn visit_entry_tag(&mut self, tag: &EntryTag) {
+        walk_entry_tag(self, tag)
+    }
+
+    fn visit_blockquote(&mut self, tags: &[SimpleTag], _author: Option<&str>) {
+        walk_simple_tags(self, tags)
+    }
+
+    fn visit_para(&mut self, tags: &[ParaTag]) {
+        for tag in tags {
+            self.visit_para_tag(tag);
+        }
+    }
+
+    fn visit_pre(&mut self, _raw_html: &str) {}
+
+    fn visit_line_break(&mut self) {}
+
+    fn visit_unknown_block(&mut self, _raw_html: &str) {}
+
+    fn visit_para_tag(&mut self, tag: &ParaTag) {
+        walk_para_tag(self, tag)
+    }
+
+    fn visit_strong(&mut self, _word: &str) {}
+
+    fn visit_boxed(&mut self, tags: &[SimpleTag]) {
+        walk_simple_tags(self, tags)
+    }
+
+    fn visit_boxed_grammar(&mut self, tags: &[SimpleTag]) {
+        walk_simple_tags(self, tags)
+    }
+
+    fn visit_dquotes(&mut self, tags: &[SimpleTag]) {
+        walk_simple_tags(self, tags)
+    }
+
+    fn visit_simple(&mut self, tags: &[SimpleTag]) {
+        walk_simple_tags(self, tags)
+    }
+
+    fn visit_simple_tag(&mut self, tag: &SimpleTag) {
+        walk_simple_tag(self, tag)
+    }
+
+    fn visit_bold(&mut self, _text: &str) {}
+
+    fn visit_boxed_plain(&mut self, _text: &str) {}
+
+    fn visit_break_tag(&mut self) {}
+
+    fn visit_emph(&mut self, _text: &str) {}
+
+    fn visit_plain(&mut self, _text: &str) {}
+
+    fn visit_small_b(&mut self, _text: &str) {}
+
+    fn visit_sub(&mut self, _text: &str) {}
+
+    fn visit_sup(&mut self, _text: &str) {}
+
+    fn visit_unknown(&mut self, _raw_html: &str) {}
+
+    fn visit_word_ref(&mut self, _tocid: u32, _text: &str) {}
+}
+
+pub fn walk_entry<V: Visit + ?Sized>(visitor: &mut V, entry: &TaggedEntry) {
+    for tag in &entry.tags {
+        visitor.visit_entry_tag(tag);
+    }
+}
+
+pub fn walk_entry_tag<V: Visit + ?Sized>(visitor: &mut V, tag: &EntryTag) {
+    match *tag {
+        EntryTag::Blockquote(ref tags, ref author) => {
+            visitor.visit_blockquote(tags, author.as_ref().map(|a| &a[..]))
+        }
+        EntryTag::Para(ref tags) => visitor.visit_para(tags),
+        EntryTag::Pre(ref raw_html) => visitor.visit_pre(raw_html),
+        EntryTag::LineBreak => visitor.visit_line_break(),
+        EntryTag::Unknown(ref raw_html) => visitor.visit_unknown_block(raw_html),
+    }
+}
+
+pub fn walk_para_tag<V: Visit + ?Sized>(visitor: &mut V, tag: &ParaTag) {
+    match *tag {
+        ParaTag::Strong(ref word) => visitor.visit_strong(word),
+        ParaTag::Boxed(ref tags) => visitor.visit_boxed(tags),
+        ParaTag::BoxedGrammar(ref tags) => visitor.visit_boxed_grammar(tags),
+        ParaTag::Dquotes(ref tags) => visitor.visit_dquotes(tags),
+        ParaTag::Simple(ref tags) => visitor.visit_simple(tags),
+    }
+}
+
+pub fn walk_simple_tags<V: Visit + ?Sized>(visitor: &mut V, tags: &[SimpleTag]) {
+    for tag in tags {
+        visitor.visit_simple_tag(tag);
+    }
+}
+
+pub fn walk_simple_tag<V: Visit + ?Sized>(visitor: &mut V, tag: &SimpleTag) {
+    match *tag {
+        SimpleTag::Bold(ref text) => visitor.visit_bold(text),
+        SimpleTag::BoxedPlain(ref text) => visitor.visit_boxed_plain(text),
+        SimpleTag::BreakTag => visitor.visit_break_tag(),
+        SimpleTag::Emph(ref text) => visitor.visit_emph(text),
+        SimpleTag::Plain(ref text) => visitor.visit_plain(text),
+        SimpleTag::SmallB(ref text) => visitor.visit_small_b(text),
+        SimpleTag::Sub(ref text) => visitor.visit_sub(text),
+        SimpleTag::Sup(ref text) => visitor.visit_sup(text),
+        SimpleTag::Unknown(ref raw_html) => visitor.visit_unknown(raw_html),
+        SimpleTag::WordRef(tocid, ref text) => visitor.visit_word_ref(tocid, text),
+    }
+}
+
+pub trait Fold<'a> {
+    fn fold_entry(&mut self, entry: TaggedEntry<'a>) -> TaggedEntry<'a> {
+        fold_entry(self, entry)
+    }
+
+    /// Fold an entry-level tag into any number of tags that replace it.
+    fn fold_entry_tag(&mut self, tag: EntryTag<'a>) -> Vec<EntryTag<'a>> {
+        match tag {
+            EntryTag::Blockquote(tags, author) => vec![self.fold_blockquote(tags, author)],
+            EntryTag::Para(tags) => self.fold_para(tags),
+            tag => vec![tag],
+        }
+    }
+
+    fn fold_blockquote(&mut self, tags: Vec<SimpleTag<'a>>, author: Option<Cow<'a, str>>)
+        -> EntryTag<'a>
+    {
+        EntryTag::Blockquote(fold_simple_tags(self, tags), author)
+    }
+
+    /// Fold the tags of a paragraph into any number of entry-level tags that replace it.
+    fn fold_para(&mut self, tags: Vec<ParaTag<'a>>) -> Vec<EntryTag<'a>> {
+        vec![EntryTag::Para(tags.into_iter().map(|tag| self.fold_para_tag(tag)).collect())]
+    }
+
+    fn fold_para_tag(&mut self, tag: ParaTag<'a>) -> ParaTag<'a> {
+        match tag {
+            ParaTag::Boxed(tags) => self.fold_boxed(tags),
+            ParaTag::BoxedGrammar(tags) => self.fold_boxed_grammar(tags),
+            ParaTag::Dquotes(tags) => self.fold_dquotes(tags),
+            ParaTag::Simple(tags) => self.fold_simple(tags),
+            tag => tag,
+        }
+    }
+
+    fn fold_boxed(&mut self, tags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::Boxed(fold_simple_tags(self, tags))
+    }
+
+    fn fold_boxed_grammar(&mut self, tags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::BoxedGrammar(fold_simple_tags(self, tags))
+    }
+
+    fn fold_dquotes(&mut self, tags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::Dquotes(fold_simple_tags(self, tags))
+    }
+
+    fn fold_simple(&mut self, tags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+        ParaTag::Simple(fold_simple_tags(self, tags))
+    }
+
+    fn fold_simple_tag(&mut self, tag: SimpleTag<'a>) -> SimpleTag<'a> {
+        tag
+    }
+}
+
+pub fn fold_entry<'a, F: Fold<'a> + ?Sized>(folder: &mut F, entry: TaggedEntry<'a>) -> TaggedEntry<'a> {
+    TaggedEntry {
+        tocid: entry.tocid,
+        tags: entry.tags.into_iter().flat_map(|tag| folder.fold_entry_tag(tag)).collect(),
+        word: entry.word,
+    }
+}
+
+pub fn fold_simple_tags<'a, F: Fold<'a> + ?Sized>(folder: &mut F, tags: Vec<SimpleTag<'a>>)
+    -> Vec<SimpleTag<'a>>
+{
+    tags.into_iter().map(|tag| folder.fold_simple_tag(tag)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Fold, Visit};
+    use parser::{ParaTag, Parser, SimpleTag};
+
+    const ENTRY: &str = "<div id=\"MBP_TOC_A\" data-ascii=\"A\">\n<p><strong>A</strong> \
+                         [<i>pl.</i> <a href=\"#MBP_TOC_B\">As</a>] See <a href=\"#MBP_TOC_C\">C</a>.</p>\n\
+                         <blockquote>\n<p>a <i>b</i></p>\n</blockquote>\n</div>\n";
+
+    struct Refs(Vec<u32>);
+
+    impl Visit for Refs {
+        fn visit_word_ref(&mut self, tocid: u32, _text: &str) {
+            self.0.push(tocid);
+        }
+    }
+
+    struct SmallRefs;
+
+    impl<'a> Fold<'a> for SmallRefs {
+        fn fold_boxed_grammar(&mut self, tags: Vec<SimpleTag<'a>>) -> ParaTag<'a> {
+            ParaTag::BoxedGrammar(tags.into_iter().map(|tag| match tag {
+                SimpleTag::WordRef(_, text) => SimpleTag::SmallB(text),
+                tag => tag,
+            }).collect())
+        }
+    }
+
+    #[test]
+    fn visit_test() {
+        let entry = Parser::new(ENTRY).next().unwrap().1.unwrap();
+        let mut refs = Refs(Vec::new());
+        refs.visit_entry(&entry);
+        assert_eq!(refs.0, [0xB, 0xC]);
+    }
+
+    #[test]
+    fn fold_test() {
+        let entry = Parser::new(ENTRY).next().unwrap().1.unwrap();
+        let folded = SmallRefs.fold_entry(entry.clone());
+        assert_eq!(folded.to_string(), ENTRY.replace("<a href=\"#MBP_TOC_B\">As</a>",
+                                                     "<small><b>As</b></small>"));
+        assert_eq!(folded.tags[1], entry.tags[1]);
+    }
+}